@@ -1,10 +1,13 @@
 use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
 use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Runtime, Emitter};
-use midir::{MidiInput, MidiInputConnection};
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tauri::{AppHandle, Runtime, Emitter, Listener, Manager};
+use tauri_plugin_log::{Target, TargetKind};
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use tokio::task::AbortHandle;
 
 // Added an ActiveMacro struct to track in-progress macros and their timeout tasks
@@ -21,14 +24,36 @@ struct BeforeActionState {
     last_executed: std::time::Instant,
     // How long to wait before executing again
     cooldown: std::time::Duration,
+    // Triggers of the group this session, counting the one that first ran before_actions
+    triggers: u32,
 }
 
-// Global settings structure
+// Global settings structure (missing fields fall back to their defaults)
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GlobalSettings {
     pub macro_trigger_delay: u64, // Delay in milliseconds
     pub enable_macro_conflict_prevention: bool,
     pub default_timeout: u32,
+    pub reconnect_interval_ms: u64, // How often to check for / retry an unplugged MIDI device (at least 100)
+    pub reconnect_max_attempts: u32, // 0 = keep retrying forever
+    pub dry_run: bool, // Report actions through dry-run-action events instead of executing them
+    pub enigo_init_delay_ms: u64, // Sleep before creating each Enigo instance; 0 = none
+    pub log_level: String, // "off", "error", "warn", "info", "debug" (MIDI traffic) or "trace"
+    pub action_timeout_ms: u64, // Give up on a single macro action after this long; 0 = no limit
+    pub max_concurrent_macros: usize, // Triggers beyond this many running ones are dropped; 0 = no limit
+    pub midi_client_name: String, // Name the app's MIDI inputs show up as in the OS MIDI graph (and DAW routing)
+    pub velocity_gamma: f64, // NoteOn velocity curve: < 1 boosts soft hits, > 1 softens them, 1 = unchanged
+    pub suppress_realtime_events: bool, // Don't send clock, active sensing etc. (0xF8-0xFF) to the frontend
+    pub emit_message_types: Vec<String>, // Only these types (e.g. "noteon", "controlchange") reach the frontend; empty = all
+    pub max_midi_events_per_100ms: u32, // Per message type; the rest are still matched but not shown; 0 = no limit
+    pub auto_connect_last_port: bool, // On startup, reconnect the last MIDI input that connected successfully
+    pub scroll_acceleration: f64, // Most a scroll amount is multiplied by when a group's triggers come fast; 1 = off
+    pub scroll_acceleration_window_ms: u64, // Triggers closer together than this speed scrolling up
+    pub scroll_acceleration_curve: f64, // 1 = linear in trigger speed; higher only accelerates the fastest spins
+    pub forbidden_regions: Vec<ForbiddenRegion>, // Mouse moves landing and clicks or drags pressing inside these are refused
+    pub action_history_size: usize, // Executed actions kept for get_action_history; 0 = don't record
+    pub default_move_duration_ms: u32, // Animate MouseMoves without a `duration` over this long; 0 = jump
 }
 
 impl Default for GlobalSettings {
@@ -37,18 +62,59 @@ impl Default for GlobalSettings {
             macro_trigger_delay: 0, // 0ms default (no delay)
             enable_macro_conflict_prevention: true,
             default_timeout: 500,
+            reconnect_interval_ms: 2000,
+            reconnect_max_attempts: 30,
+            dry_run: false,
+            enigo_init_delay_ms: 0,
+            log_level: "info".to_string(),
+            action_timeout_ms: 0,
+            max_concurrent_macros: 16,
+            midi_client_name: DEFAULT_MIDI_CLIENT_NAME.to_string(),
+            velocity_gamma: 1.0,
+            suppress_realtime_events: true,
+            emit_message_types: Vec::new(),
+            max_midi_events_per_100ms: 50,
+            auto_connect_last_port: true,
+            scroll_acceleration: 1.0,
+            scroll_acceleration_window_ms: 150,
+            scroll_acceleration_curve: 2.0,
+            forbidden_regions: Vec::new(),
+            action_history_size: 200,
+            default_move_duration_ms: 0,
         }
     }
 }
 
+// Screen rectangle mouse moves must not land in (e.g. a kiosk's shut-down button).
+// Coordinates are global like MouseMove's, or relative to `monitor` when it is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForbiddenRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+const DEFAULT_MIDI_CLIENT_NAME: &str = "opengrader-midi-input";
+
+// MIDI client name from the settings, falling back to the default when left blank
+fn midi_client_name() -> String {
+    let name = lock_or_recover(&APP_STATE.global_settings).midi_client_name.trim().to_string();
+    if name.is_empty() { DEFAULT_MIDI_CLIENT_NAME.to_string() } else { name }
+}
+
 // Shared state for the application - removed Enigo from here
 pub struct AppState {
     // Removed enigo from here since it's not thread-safe
-    midi_connection: Mutex<Option<MidiInputConnection<()>>>,
-    midi_ports: Mutex<Vec<(String, usize)>>, // Store (port_name, index) pairs
+    midi_connections: Mutex<HashMap<usize, OpenMidiInput>>, // Open inputs keyed by port index
+    midi_ports: Mutex<Vec<MidiPortInfo>>, // Input ports as of the last listing, by index
     registered_macros: Mutex<Vec<MacroConfig>>, // Added to store macros
-    mouse_state: Mutex<HashMap<MouseButton, bool>>, // Track which buttons are pressed
-    key_state: Mutex<HashMap<Key, bool>>, // Track which keys are pressed
+    mouse_state: Mutex<HashMap<MouseButton, Option<String>>>, // Pressed buttons and the macro holding them
+    key_state: Mutex<HashMap<Key, Option<String>>>, // Pressed keys and the macro holding them
 
     // Track active macros by their ID
     active_macros: Mutex<HashMap<String, ActiveMacro>>,
@@ -62,12 +128,72 @@ pub struct AppState {
     group_sessions: Mutex<HashMap<String, u64>>, 
     // Notifier to signal completion of before_actions per group/session
     before_notifiers: Mutex<HashMap<String, std::sync::Arc<tokio::sync::Notify>>>,
+    // Pending 14-bit CC MSBs keyed by (channel, controller), waiting for their LSB
+    pending_cc_msb: Mutex<HashMap<(u8, u8), (u8, std::time::Instant)>>,
+    // NRPN parameter / data entry bytes received so far, per channel
+    nrpn_states: Mutex<HashMap<u8, NrpnState>>,
+    // App config directory resolved during setup, used for persisting macros
+    config_dir: Mutex<Option<std::path::PathBuf>>,
+    // Counter handing out watchdog generations, and the live generation per port index
+    midi_watchdog_generation: Mutex<u64>,
+    midi_watchdogs: Mutex<HashMap<usize, u64>>,
+    // Shared RNG for randomized behavior such as delay jitter
+    rng: Mutex<StdRng>,
+    // One-shot capture for MIDI learn: the next parsed message goes here instead of macro matching
+    midi_learn_sender: Mutex<Option<tokio::sync::oneshot::Sender<MidiData>>>,
+    // Last signed pitch-bend value per channel, used to detect threshold crossings
+    last_pitch_bend: Mutex<HashMap<u8, i16>>,
+    // Last pressure per (channel, note) — note None for channel pressure — for threshold crossings
+    last_pressure: Mutex<HashMap<(u8, Option<u8>), u8>>,
+    // Last main-actions run per group, for scroll acceleration
+    last_scroll_triggers: Mutex<HashMap<String, std::time::Instant>>,
+    // Last accepted MIDI trigger per macro id, for per-macro debounce
+    last_macro_triggers: Mutex<HashMap<String, std::time::Instant>>,
+    // On/off state of toggle macros by macro id (absent = off, so the next press runs `actions`)
+    macro_toggle_states: Mutex<HashMap<String, bool>>,
+    // Output port used by SendMidi actions (e.g. to light controller LEDs)
+    midi_output: Mutex<Option<MidiOutputConnection>>,
+    // Active macro layer; macros with a `layer` only trigger while it is active
+    current_layer: Mutex<String>,
+    // Key names latched by LatchModifier, held around the next key-producing action of any macro
+    latched_modifiers: Mutex<Vec<String>>,
+    // Momentary macros whose note is currently held down
+    held_momentary_macros: Mutex<HashSet<String>>,
+    // While set, the scheduler doesn't fire scheduled macros
+    schedules_paused: Mutex<bool>,
+    // While set, MIDI messages are still shown but no longer trigger macros
+    macros_paused: Mutex<bool>,
+    // Inverses of the reversible actions of each group's last main-actions run, in execution order
+    undo_stacks: Mutex<HashMap<GroupId, Vec<(ActionType, ActionParams)>>>,
+    // Number of handle_macro_trigger tasks currently running
+    in_flight_triggers: Mutex<usize>,
+    // When the last midi-flood-detected event was emitted, to throttle it
+    last_flood_event: Mutex<Option<std::time::Instant>>,
+    // Per message type: start of the current rate-limit window and events emitted in it
+    midi_emit_windows: Mutex<HashMap<&'static str, (std::time::Instant, u32)>>,
+    // Most recent executed macro actions, oldest first, capped at action_history_size
+    action_history: Mutex<std::collections::VecDeque<ActionHistoryEntry>>,
+    // Recent MIDI timing clock ticks, for tempo-synced delays
+    midi_clock: Mutex<std::collections::VecDeque<std::time::Instant>>,
+    // Message and trigger counters for get_midi_stats, reset when a port connects
+    midi_stats: Mutex<MidiStats>,
+    // Incoming MIDI captured by start_midi_recording, for saving and replaying
+    midi_recording: Mutex<MidiRecording>,
+    // Playback started by replay_recording, until it finishes or stop_replay aborts it
+    replay_task: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+    // Signalled when the frontend first pushes its settings; the startup auto-connect waits for it
+    settings_received: tokio::sync::Notify,
+    // Outcome of the startup auto-connect, kept for a frontend that wasn't listening yet
+    auto_connect_status: Mutex<Option<String>>,
 }
 
+const DEFAULT_LAYER: &str = "default";
+const LAYER_CHANGED_EVENT: &str = "layer-changed";
+
 static APP_STATE: Lazy<Arc<AppState>> = Lazy::new(|| {
     Arc::new(AppState {
         // Removed enigo initialization
-        midi_connection: Mutex::new(None),
+        midi_connections: Mutex::new(HashMap::new()),
         midi_ports: Mutex::new(Vec::new()),
         mouse_state: Mutex::new(HashMap::new()),
         key_state: Mutex::new(HashMap::new()),
@@ -79,44 +205,246 @@ static APP_STATE: Lazy<Arc<AppState>> = Lazy::new(|| {
         last_group_triggers: Mutex::new(HashMap::new()),
     group_sessions: Mutex::new(HashMap::new()),
     before_notifiers: Mutex::new(HashMap::new()),
+        pending_cc_msb: Mutex::new(HashMap::new()),
+        nrpn_states: Mutex::new(HashMap::new()),
+        config_dir: Mutex::new(None),
+        midi_watchdog_generation: Mutex::new(0),
+        midi_watchdogs: Mutex::new(HashMap::new()),
+        rng: Mutex::new(StdRng::from_entropy()),
+        midi_learn_sender: Mutex::new(None),
+        last_pitch_bend: Mutex::new(HashMap::new()),
+        last_pressure: Mutex::new(HashMap::new()),
+        last_scroll_triggers: Mutex::new(HashMap::new()),
+        last_macro_triggers: Mutex::new(HashMap::new()),
+        macro_toggle_states: Mutex::new(HashMap::new()),
+        midi_output: Mutex::new(None),
+        current_layer: Mutex::new(DEFAULT_LAYER.to_string()),
+        latched_modifiers: Mutex::new(Vec::new()),
+        held_momentary_macros: Mutex::new(HashSet::new()),
+        schedules_paused: Mutex::new(false),
+        macros_paused: Mutex::new(false),
+        undo_stacks: Mutex::new(HashMap::new()),
+        in_flight_triggers: Mutex::new(0),
+        last_flood_event: Mutex::new(None),
+        midi_emit_windows: Mutex::new(HashMap::new()),
+        midi_stats: Mutex::new(MidiStats::new()),
+        midi_clock: Mutex::new(std::collections::VecDeque::new()),
+        action_history: Mutex::new(std::collections::VecDeque::new()),
+        midi_recording: Mutex::new(MidiRecording::default()),
+        replay_task: Mutex::new(None),
+        settings_received: tokio::sync::Notify::new(),
+        auto_connect_status: Mutex::new(None),
     })
 });
 
+// Lock a mutex, recovering the data if a previous holder panicked.
+// Without this, one panicking task would make every later lock panic too
+// and the app would silently stop triggering macros.
+fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("Recovering from a poisoned mutex after a panic");
+        poisoned.into_inner()
+    })
+}
+
 // Helper function to create Enigo instances on-demand
 fn create_enigo() -> Enigo {
-    println!("Creating new Enigo instance...");
-    // Add a small delay to ensure proper initialization
-    std::thread::sleep(std::time::Duration::from_millis(50));
+    log::debug!("Creating new Enigo instance...");
+    // Optional settle time for platforms that race on initialization
+    let init_delay_ms = lock_or_recover(&APP_STATE.global_settings).enigo_init_delay_ms;
+    if init_delay_ms > 0 {
+        std::thread::sleep(std::time::Duration::from_millis(init_delay_ms));
+    }
     let enigo = Enigo::new();
-    println!("Enigo instance created successfully");
+    log::debug!("Enigo instance created successfully");
     enigo
 }
 
+// Wildcard value for MacroConfig.midi_channel
+pub const MIDI_CHANNEL_ANY: u8 = 0;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacroConfig {
     pub id: String,
+    // Schema version the config was written with (0 = before versioning); see migrate_macro_config.
+    // Optional fields missing from older or hand-edited JSON take their defaults; the trigger
+    // (midi_note, midi_channel) is required so a partial config can't fire on note 0 everywhere.
+    #[serde(default)]
+    pub config_version: u32,
+    #[serde(default)]
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub groupId: Option<String>, // Added for encoder groups to share state
-    pub midi_note: u8,
-    pub midi_channel: u8,
+    pub midi_note: u8, // Note number, CC number, or program number for Program Change
+    pub midi_channel: u8, // 1-16, or 0 (MIDI_CHANNEL_ANY) to match every channel
+    // Inclusive note range for NoteOn/NoteOff; replaces midi_note when both are set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_note_min: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_note_max: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub midi_value: Option<u8>,
+    // Inclusive CC value zone (e.g. 85-127 for a fader's top third); a missing bound is open.
+    // Used instead of midi_value when either is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_value_min: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_value_max: Option<u8>,
+    // CC values within ± this of midi_value also match (for jittery faders); absent = exact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_value_tolerance: Option<u8>,
+    // 14-bit NRPN parameter number (CC 99/98); fires on each value sent for it (CC 6/38)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nrpn_param: Option<u16>,
+    // 14-bit CC value (0-16383) assembled from MSB on midi_note and LSB on midi_note + 32
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_value_14bit: Option<u16>,
+    // Signed pitch-bend threshold (-8192..8191); fires when the wheel crosses it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_threshold: Option<i16>,
+    // Crossing direction for pitch_threshold (defaults to away from center)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_direction: Option<PitchDirection>,
+    // Channel pressure / poly aftertouch rising to this value fires the macro (poly also matches the note);
+    // it re-arms once the pressure drops back below
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure_threshold: Option<u8>,
+    // Fires on SysEx messages starting with these bytes (including the leading 0xF0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sysex_pattern: Option<Vec<u8>>,
+    // Require the whole SysEx message to equal sysex_pattern instead of just starting with it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sysex_exact: Option<bool>,
+    #[serde(default)]
     pub actions: Vec<MacroAction>, // Added
+    // Run `actions` this many times per trigger; 0 repeats until the group is retriggered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<u32>,
     // New fields for before/after actions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub before_actions: Option<Vec<MacroAction>>,
+    // Also re-run before_actions on every Nth trigger of the session (1, N+1, 2N+1, ...);
+    // absent = only once per session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_every_n: Option<u32>,
+    // Upper bound on a trigger's before + main actions; the rest is abandoned, inputs the
+    // macro holds are released and macro-timeout is emitted. A flow cut off in its main
+    // actions still gets its after_actions; one cut off in before_actions starts over on
+    // the next trigger. Absent or 0 = no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_flow_ms: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub after_actions: Option<Vec<MacroAction>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub timeout: Option<u32>, // in milliseconds
+    pub timeout: Option<u32>, // in milliseconds; falls back to GlobalSettings.default_timeout when after_actions exist
+    // Ignore repeat MIDI triggers of this macro within this many ms (e.g. stray duplicate NoteOns)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u32>,
+    // Disabled macros stay registered but are skipped by MIDI matching (absent = enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // How CC values on midi_note are read; relative modes fire on any non-zero delta
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoder_mode: Option<EncoderMode>,
+    // Relative encoders run these instead of `actions` depending on the delta's sign
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub increment_actions: Option<Vec<MacroAction>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decrement_actions: Option<Vec<MacroAction>>,
+    // Alternate between `actions` (odd presses) and `alternate_actions` (even presses)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toggle: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alternate_actions: Option<Vec<MacroAction>>,
+    // Which note edge fires the macro; absent matches both NoteOn and NoteOff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_on: Option<TriggerEdge>,
+    // Only trigger while this layer is active; absent = global, active on every layer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    // Hold-mode inputs pressed by the macro are released on the matching NoteOff
+    // instead of it triggering the macro again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub momentary: Option<bool>,
+    // Also trigger the macro on a timer, independent of MIDI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScheduleSpec {
+    // Every `ms` milliseconds, counted from when the scheduler first sees the macro
+    Interval { ms: u64 },
+    // Once a day at this local time (hour 0-23, minute 0-59)
+    Daily { hour: u8, minute: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TriggerEdge {
+    NoteOn,
+    NoteOff,
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EncoderMode {
+    #[default]
+    Absolute,
+    // 1..63 clockwise, 127..65 counter-clockwise (127 = -1)
+    RelativeTwosComplement,
+    // Bit 6 is the sign: 1..63 clockwise, 65..127 counter-clockwise (65 = -1)
+    RelativeSignedBit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PitchDirection {
+    Up,
+    Down,
 }
 
 // New struct to represent an action within before/after actions arrays
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacroAction {
     pub action_type: ActionType,
+    #[serde(default)]
     pub action_params: ActionParams,
+    // Maps the triggering NoteOn velocity onto one of the action's parameters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity_scaling: Option<VelocityScaling>,
+    // Only run this action when the condition holds at execution time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<ActionCondition>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionCondition {
+    // Case-insensitive substring of the frontmost application's name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_app: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VelocityScaling {
+    pub target: ScalingTarget,
+    // Parameter value at velocity 0 and at velocity 127
+    pub min: f64,
+    pub max: f64,
+    #[serde(default)]
+    pub curve: ScalingCurve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScalingTarget {
+    Amount,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ScalingCurve {
+    #[default]
+    Linear,
+    Exponential,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +457,22 @@ pub enum ActionType {
     MouseRelease,
     MouseDrag,
     Delay,
+    TypeText,
+    MouseMoveSmooth,
+    KeyTap, // Tap `key` `amount` times, `duration` ms apart
+    SendMidi, // Send `midi_bytes` to the connected MIDI output
+    WithModifiers, // Hold `modifiers` while running the nested `actions`
+    RawKey, // Click the platform keycode in `amount` (virtual-key on Windows, macOS keycode, X11 keycode)
+    ScrollSmooth, // Scroll `amount` clicks towards `direction`, spread over `duration` ms
+    SetLayer, // Make `text` the active macro layer
+    WaitForPixel, // Wait until the pixel at x/y matches color `text` (± `amount`), failing after `duration` ms
+    RunMacro, // Run the main `actions` of the registered macro whose id is `text`
+    KeepAwake, // Nudge the mouse 1px and back every `amount` ms (default 60000) for `duration` ms
+    MoveAndClick, // Move to x/y (or by them when `relative`), let the cursor settle, then click `button` `amount` times
+    KeyHoldFor, // Hold `key` down for `duration` ms, then release it
+    FocusWindow, // Bring the first window whose title contains `text` (case-insensitive) to the front
+    LatchModifier, // Latch `key` (sticky keys) for the next key-producing action; latching it again unlatches
+    DragTo, // Press `button` at x/y (default: where the cursor is), move to end_x/end_y over `duration` ms, release
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -153,6 +497,32 @@ pub struct ActionParams {
     pub duration: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub amount: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub easing: Option<String>, // "linear" (default) or "ease-in-out"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<u32>, // Delay: randomize duration by up to ± this many ms
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<u32>, // Absolute mouse moves: x/y relative to this monitor (index from list_monitors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub midi_bytes: Option<Vec<u8>>, // SendMidi: raw message, e.g. [0x90, 36, 127]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<MacroAction>>, // WithModifiers: actions run while the modifiers are held
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>, // ScrollSmooth: "up", "down" (default), "left" or "right"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hold_duration_ms: Option<u32>, // KeyCombination: keep all keys down this long before releasing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f32>, // Chance (0.0-1.0) that the action runs each time; absent = always
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_x: Option<i32>, // DragTo: where the drag ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_y: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_value: Option<String>, // Delay: musical length at the MIDI clock's tempo, e.g. "1/4", "1/8d", "1/8t"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<String>, // Absolute mouse moves and DragTo: x/y (and end_x/end_y) relative to the first window whose title contains this
 }
 
 impl Default for ActionParams {
@@ -168,6 +538,19 @@ impl Default for ActionParams {
             hold: None,
             duration: None,
             amount: None,
+            text: None,
+            easing: None,
+            jitter_ms: None,
+            monitor: None,
+            midi_bytes: None,
+            actions: None,
+            direction: None,
+            hold_duration_ms: None,
+            probability: None,
+            end_x: None,
+            end_y: None,
+            note_value: None,
+            window_title: None,
         }
     }
 }
@@ -220,6 +603,8 @@ fn string_to_key(key: &str) -> Option<Key> {
         "arrowright" => Some(Key::Layout('→')),
         "arrowup" => Some(Key::Layout('↑')),
         "arrowdown" => Some(Key::Layout('↓')),
+        // Media keys
+        name if MEDIA_KEY_NAMES.contains(&name) => media_key(name),
         // Additional system keys
         s if s.len() == 1 => {
             let c = s.chars().next()?;
@@ -233,140 +618,908 @@ fn string_to_key(key: &str) -> Option<Key> {
     }
 }
 
+const MEDIA_KEY_NAMES: [&str; 7] = [
+    "medianext", "mediaprev", "mediaplaypause", "mediastop", "volumeup", "volumedown", "volumemute",
+];
+
+// Enigo 0.1 has no named media keys, so they're sent as raw platform keycodes
+#[cfg(target_os = "windows")]
+fn media_key(name: &str) -> Option<Key> {
+    // Win32 virtual-key codes
+    match name {
+        "medianext" => Some(Key::Raw(0xB0)),
+        "mediaprev" => Some(Key::Raw(0xB1)),
+        "mediastop" => Some(Key::Raw(0xB2)),
+        "mediaplaypause" => Some(Key::Raw(0xB3)),
+        "volumemute" => Some(Key::Raw(0xAD)),
+        "volumedown" => Some(Key::Raw(0xAE)),
+        "volumeup" => Some(Key::Raw(0xAF)),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn media_key(name: &str) -> Option<Key> {
+    // Carbon virtual keycodes; transport keys are system-defined events with no keycode
+    match name {
+        "volumeup" => Some(Key::Raw(0x48)),
+        "volumedown" => Some(Key::Raw(0x49)),
+        "volumemute" => Some(Key::Raw(0x4A)),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn media_key(name: &str) -> Option<Key> {
+    // X11 keycodes of the XF86Audio* keys under the standard evdev keymap
+    match name {
+        "volumemute" => Some(Key::Raw(121)),
+        "volumedown" => Some(Key::Raw(122)),
+        "volumeup" => Some(Key::Raw(123)),
+        "medianext" => Some(Key::Raw(171)),
+        "mediaplaypause" => Some(Key::Raw(172)),
+        "mediaprev" => Some(Key::Raw(173)),
+        "mediastop" => Some(Key::Raw(174)),
+        _ => None,
+    }
+}
+
+// Name of the application that currently has focus, e.g. "Code" or "Ableton Live 12 Suite"
+#[cfg(target_os = "windows")]
+fn frontmost_app_name() -> Option<String> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pid == 0 {
+            return None;
+        }
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+        let mut buffer = [0u16; 260];
+        let mut len = buffer.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len);
+        CloseHandle(process);
+        if ok == 0 {
+            return None;
+        }
+        // Report the executable name without its directory or ".exe"
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        std::path::Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn frontmost_app_name() -> Option<String> {
+    let output = std::process::Command::new("osascript")
+        .args(["-e", "tell application \"System Events\" to get name of first application process whose frontmost is true"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn frontmost_app_name() -> Option<String> {
+    // X11 only; relies on xdotool being installed
+    let output = std::process::Command::new("xdotool")
+        .args(["getactivewindow", "getwindowpid"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let pid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    let name = name.trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+// First visible top-level window whose title contains `title` (case-insensitive)
+#[cfg(target_os = "windows")]
+fn find_window(title: &str) -> Result<windows_sys::Win32::Foundation::HWND, String> {
+    use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, IsWindowVisible};
+
+    struct Search {
+        needle: String,
+        found: HWND,
+    }
+
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam as *mut Search);
+        if IsWindowVisible(hwnd) == 0 {
+            return 1;
+        }
+        let mut buffer = [0u16; 512];
+        let len = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+        if len > 0 && String::from_utf16_lossy(&buffer[..len as usize]).to_lowercase().contains(&search.needle) {
+            search.found = hwnd;
+            return 0; // Stop enumerating
+        }
+        1
+    }
+
+    let mut search = Search { needle: title.to_lowercase(), found: std::ptr::null_mut() };
+    unsafe {
+        EnumWindows(Some(visit), &mut search as *mut Search as LPARAM);
+    }
+    if search.found.is_null() {
+        return Err(format!("No window found with a title containing '{}'", title));
+    }
+    Ok(search.found)
+}
+
+// Bring the first visible top-level window whose title contains `title` (case-insensitive) to the front
+#[cfg(target_os = "windows")]
+fn focus_window(title: &str) -> Result<(), String> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{IsIconic, SetForegroundWindow, ShowWindow, SW_RESTORE};
+
+    let hwnd = find_window(title)?;
+    unsafe {
+        if IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        if SetForegroundWindow(hwnd) == 0 {
+            return Err(format!("Windows refused to bring the window matching '{}' to the front", title));
+        }
+    }
+    Ok(())
+}
+
+// Top-left corner of the first window whose title contains `title`, in screen coordinates.
+// GetWindowRect includes the invisible resize borders of Windows 10/11 (about 7px), so the
+// visible frame DWM reports is used, falling back to GetWindowRect without composition.
+#[cfg(target_os = "windows")]
+fn window_origin(title: &str) -> Result<(i32, i32), String> {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowRect;
+
+    let hwnd = find_window(title)?;
+    let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    let frame = unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS as u32,
+            &mut rect as *mut RECT as *mut core::ffi::c_void,
+            std::mem::size_of::<RECT>() as u32,
+        )
+    };
+    if frame != 0 && unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+        return Err(format!("Failed to read the position of the window matching '{}'", title));
+    }
+    Ok((rect.left, rect.top))
+}
+
+// Run `on_match` against the first window (w) of a visible process (p) whose title contains
+// `title` and return what it returns; an empty result means no window matched
+#[cfg(target_os = "macos")]
+fn run_window_script(title: &str, on_match: &[&str]) -> Result<String, String> {
+    // The title is passed as an argument rather than spliced into the script
+    let mut script = vec![
+        "on run argv",
+        "set wanted to item 1 of argv",
+        "tell application \"System Events\"",
+        "repeat with p in (every application process whose visible is true)",
+        "repeat with w in (every window of p)",
+        "if name of w contains wanted then",
+    ];
+    script.extend_from_slice(on_match);
+    script.extend_from_slice(&["end if", "end repeat", "end repeat", "end tell", "return \"\"", "end run"]);
+
+    let mut command = std::process::Command::new("osascript");
+    for line in script {
+        command.args(["-e", line]);
+    }
+    let output = command
+        .arg(title)
+        .output()
+        .map_err(|e| format!("Failed to run osascript: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("osascript failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if result.is_empty() {
+        return Err(format!("No window found with a title containing '{}'", title));
+    }
+    Ok(result)
+}
+
+#[cfg(target_os = "macos")]
+fn focus_window(title: &str) -> Result<(), String> {
+    run_window_script(title, &[
+        "set frontmost of p to true",
+        "perform action \"AXRaise\" of w",
+        "return name of w",
+    ])?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn window_origin(title: &str) -> Result<(i32, i32), String> {
+    let position = run_window_script(title, &[
+        "set {x, y} to position of w",
+        "return (x as text) & \",\" & (y as text)",
+    ])?;
+    position
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Unexpected window position from osascript: {}", position))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn focus_window(title: &str) -> Result<(), String> {
+    // X11 only; wmctrl -a matches a case-insensitive substring of the title
+    let status = std::process::Command::new("wmctrl")
+        .args(["-a", title])
+        .status()
+        .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+    if !status.success() {
+        return Err(format!("No window found with a title containing '{}'", title));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn window_origin(title: &str) -> Result<(i32, i32), String> {
+    // X11 only; each wmctrl -lG line is "id desktop x y width height host title..."
+    let output = std::process::Command::new("wmctrl")
+        .arg("-lG")
+        .output()
+        .map_err(|e| format!("Failed to run wmctrl (is it installed?): {}", e))?;
+    if !output.status.success() {
+        return Err(format!("wmctrl failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let needle = title.to_lowercase();
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = fields.get(7..)?.join(" ");
+            if !name.to_lowercase().contains(&needle) {
+                return None;
+            }
+            Some((fields[2].parse().ok()?, fields[3].parse().ok()?))
+        })
+        .ok_or_else(|| format!("No window found with a title containing '{}'", title))
+}
+
+fn condition_matches(condition: &ActionCondition, active_app: Option<&str>) -> bool {
+    match &condition.active_app {
+        Some(wanted) => active_app.is_some_and(|app| app.to_lowercase().contains(&wanted.to_lowercase())),
+        None => true,
+    }
+}
+
+// Inverse of string_to_key, using its canonical names; keys it can't produce use their Debug name
+fn key_to_string(key: Key) -> String {
+    let name = match key {
+        Key::Backspace => "backspace",
+        Key::Tab => "tab",
+        Key::Return => "enter",
+        Key::Escape => "escape",
+        Key::Space => "space",
+        Key::CapsLock => "capslock",
+        Key::Shift => "shift",
+        Key::Control => "ctrl",
+        Key::Alt => "alt",
+        Key::Meta => "meta",
+        Key::Delete => "delete",
+        Key::Home => "home",
+        Key::End => "end",
+        Key::PageUp => "pageup",
+        Key::PageDown => "pagedown",
+        Key::Layout('←') => "leftarrow",
+        Key::Layout('→') => "rightarrow",
+        Key::Layout('↑') => "uparrow",
+        Key::Layout('↓') => "downarrow",
+        Key::Layout(c) => return c.to_string(),
+        Key::Raw(code) => {
+            return MEDIA_KEY_NAMES
+                .iter()
+                .find(|name| media_key(name) == Some(key))
+                .map_or_else(|| format!("raw:{}", code), |name| name.to_string());
+        },
+        other => return format!("{:?}", other).to_lowercase(),
+    };
+    name.to_string()
+}
+
+fn mouse_button_to_string(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "left".to_string(),
+        MouseButton::Right => "right".to_string(),
+        MouseButton::Middle => "middle".to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
 // Convert string button name to Enigo MouseButton
 fn string_to_mouse_button(button: &str) -> Option<MouseButton> {
     match button.to_lowercase().as_str() {
         "left" => Some(MouseButton::Left),
         "right" => Some(MouseButton::Right),
         "middle" => Some(MouseButton::Middle),
+        // Side buttons; Enigo only has these on Windows and Linux (see side_button_number for macOS)
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        "back" | "x1" => Some(MouseButton::Back),
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        "forward" | "x2" => Some(MouseButton::Forward),
         _ => None,
     }
 }
 
-// Command to execute an action based on a macro
-#[tauri::command]
-async fn execute_action<R: Runtime>(app_handle: AppHandle<R>, action_type: ActionType, params: ActionParams) -> Result<(), String> {
-    execute_action_safe(action_type, params, Some(app_handle)).await
+// macOS button number of a side button name, for clicks Enigo can't send there
+#[cfg(target_os = "macos")]
+fn side_button_number(button: &str) -> Option<u32> {
+    match button.to_lowercase().as_str() {
+        "back" | "x1" => Some(3),
+        "forward" | "x2" => Some(4),
+        _ => None,
+    }
 }
 
-// Internal implementation that can be called from different contexts
-fn execute_action_impl(action_type: ActionType, params: ActionParams) -> Result<(), String> {
-    // Create a new Enigo instance for each action execution
-    println!("Creating new Enigo instance for action: {:?}", action_type);
-    let mut enigo = create_enigo();
-    
-    match action_type {
-        ActionType::MouseMove => {
-            let x = params.x.ok_or("Missing x parameter for MouseMove")?;
-            let y = params.y.ok_or("Missing y parameter for MouseMove")?;
-            let relative = params.relative.unwrap_or(false);
-            println!("Executing MouseMove: x={}, y={}, relative={}", x, y, relative);
-            if relative {
-                enigo.mouse_move_relative(x, y);
-            } else {
-                enigo.mouse_move_to(x, y);
+// Click a mouse button by number at the cursor position through Quartz events
+#[cfg(target_os = "macos")]
+fn click_mouse_button_number(button_number: u32) -> Result<(), String> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CGPoint {
+        x: f64,
+        y: f64,
+    }
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventCreate(source: *const c_void) -> *mut c_void;
+        fn CGEventGetLocation(event: *mut c_void) -> CGPoint;
+        fn CGEventCreateMouseEvent(source: *const c_void, mouse_type: u32, location: CGPoint, button: u32) -> *mut c_void;
+        fn CGEventPost(tap: u32, event: *mut c_void);
+    }
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+    const OTHER_MOUSE_DOWN: u32 = 25;
+    const OTHER_MOUSE_UP: u32 = 26;
+    const HID_EVENT_TAP: u32 = 0;
+
+    unsafe {
+        let current = CGEventCreate(std::ptr::null());
+        if current.is_null() {
+            return Err("Failed to read the cursor position".to_string());
+        }
+        let location = CGEventGetLocation(current);
+        CFRelease(current);
+
+        for mouse_type in [OTHER_MOUSE_DOWN, OTHER_MOUSE_UP] {
+            let event = CGEventCreateMouseEvent(std::ptr::null(), mouse_type, location, button_number);
+            if event.is_null() {
+                return Err(format!("Failed to create an event for mouse button {}", button_number));
+            }
+            CGEventPost(HID_EVENT_TAP, event);
+            CFRelease(event);
+        }
+    }
+    Ok(())
+}
+
+// Pause between the clicks of a double/triple click
+const MULTI_CLICK_INTERVAL_MS: u64 = 30;
+// Pause between MoveAndClick's move and click so the OS registers the new cursor position first
+const MOVE_SETTLE_MS: u64 = 15;
+
+// Click `button` `count` times (at least once), releasing it first if a hold has it down.
+// The gap between clicks stays well inside every OS's double-click time.
+fn click_n(enigo: &mut impl MouseControllable, button: MouseButton, count: i32) {
+    if lock_or_recover(&APP_STATE.mouse_state).remove(&button).is_some() {
+        enigo.mouse_up(button);
+    }
+    for click in 0..count.max(1) {
+        if click > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(MULTI_CLICK_INTERVAL_MS));
+        }
+        enigo.mouse_click(button);
+    }
+}
+
+// Scroll clicks for a scroll-* MouseClick without an amount
+const DEFAULT_SCROLL_CLICK_AMOUNT: i32 = 3;
+
+// Pseudo-buttons accepted by MouseClick that scroll instead of clicking
+fn is_scroll_button(button: &str) -> bool {
+    matches!(button, "scroll-up" | "scroll-down" | "scroll-left" | "scroll-right")
+}
+
+// Resolve a ScrollSmooth direction into (horizontal, sign) for mouse_scroll_x/y
+fn parse_scroll_direction(direction: Option<&str>) -> Result<(bool, i32), String> {
+    match direction.map(|d| d.to_lowercase()).as_deref() {
+        None | Some("down") => Ok((false, 1)),
+        Some("up") => Ok((false, -1)),
+        Some("right") => Ok((true, 1)),
+        Some("left") => Ok((true, -1)),
+        Some(other) => Err(format!("Invalid scroll direction: {}", other)),
+    }
+}
+
+// Check that every key/button an action references resolves, so broken macros
+// are reported up front instead of failing at trigger time
+fn validate_action(action: &MacroAction) -> Result<(), String> {
+    let params = &action.action_params;
+    if params.probability.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+        return Err("probability must be between 0.0 and 1.0".to_string());
+    }
+    match action.action_type {
+        ActionType::KeyPress | ActionType::KeyRelease | ActionType::KeyTap | ActionType::KeyHoldFor | ActionType::LatchModifier => {
+            let key = params.key.as_deref().ok_or("missing key")?;
+            string_to_key(key).ok_or_else(|| format!("invalid key '{}'", key))?;
+        },
+        ActionType::KeyCombination => {
+            let keys = params.keys.as_ref().ok_or("missing keys")?;
+            for key in keys {
+                string_to_key(key).ok_or_else(|| format!("invalid key '{}' in combination", key))?;
             }
-            println!("MouseMove completed successfully");
-            Ok(())
         },
         ActionType::MouseClick => {
-            let button_str = params.button.ok_or("Missing button parameter")?;
-            
-            // Handle scroll actions
-            if button_str == "scroll-up" || button_str == "scroll-down" {
-                let amount = params.amount.unwrap_or(3);
-                let scroll_amount = if button_str == "scroll-up" { -amount } else { amount };
-                
-                println!("Executing mouse scroll: direction={}, amount={}", button_str, scroll_amount);
-                enigo.mouse_scroll_y(scroll_amount);
-                println!("Mouse scroll completed successfully");
+            let button = params.button.as_deref().ok_or("missing button")?;
+            #[cfg(target_os = "macos")]
+            if side_button_number(button).is_some() {
                 return Ok(());
             }
-            
-            // Handle regular mouse clicks
-            let button = string_to_mouse_button(&button_str)
-                .ok_or_else(|| format!("Invalid mouse button: {}", button_str))?;
-            
-            if params.hold == Some(true) {
-                let mut mouse_state = APP_STATE.mouse_state.lock().unwrap();
-                if !mouse_state.get(&button).unwrap_or(&false) {
-                    enigo.mouse_down(button);
-                    mouse_state.insert(button, true);
-                    println!("Mouse {:?} pressed and tracked", button);
-                } else {
-                    println!("Mouse {:?} already pressed, skipping", button);
-                }
-            } else {
-                // For regular clicks, always release first to be safe
-                let mut mouse_state = APP_STATE.mouse_state.lock().unwrap();
-                if *mouse_state.get(&button).unwrap_or(&false) {
-                    enigo.mouse_up(button);
-                    mouse_state.insert(button, false);
-                }
-                enigo.mouse_click(button);
+            if !is_scroll_button(button) && string_to_mouse_button(button).is_none() {
+                return Err(format!("invalid mouse button '{}'", button));
             }
-            Ok(())
         },
-        ActionType::KeyPress => {
-            let key_str = params.key.ok_or("Missing key parameter for KeyPress")?;
-            let key = string_to_key(&key_str)
-                .ok_or_else(|| format!("Invalid key: {}", key_str))?;
-            
-            if params.hold == Some(true) {
-                let mut key_state = APP_STATE.key_state.lock().unwrap();
-                if !key_state.get(&key).unwrap_or(&false) {
-                    println!("Executing KeyPress with hold: key={:?}", key);
-                    enigo.key_down(key);
-                    key_state.insert(key, true);
-                    println!("Key {:?} pressed and held, tracked in state", key);
-                } else {
-                    println!("Key {:?} already held, skipping", key);
-                }
-            } else {
-                // For regular key presses, always release first to be safe
-                let mut key_state = APP_STATE.key_state.lock().unwrap();
-                if *key_state.get(&key).unwrap_or(&false) {
-                    enigo.key_up(key);
-                    key_state.insert(key, false);
-                }
-            println!("Executing KeyPress: key={:?}", key);
-            enigo.key_click(key);
-            println!("KeyPress completed successfully");
+        ActionType::SendMidi => {
+            let bytes = params.midi_bytes.as_deref().ok_or("missing midi_bytes")?;
+            if !matches!(bytes.first(), Some(0x80..=0xFF)) {
+                return Err("midi_bytes must start with a status byte (0x80-0xFF)".to_string());
             }
-            Ok(())
         },
-        ActionType::KeyRelease => {
-            let key_str = params.key.ok_or("Missing key parameter for KeyRelease")?;
-            let key = string_to_key(&key_str)
-                .ok_or_else(|| format!("Invalid key: {}", key_str))?;
-            
-            let mut key_state = APP_STATE.key_state.lock().unwrap();
-            if *key_state.get(&key).unwrap_or(&false) {
-                println!("Executing KeyRelease: key={:?}", key);
-                enigo.key_up(key);
-                key_state.insert(key, false);
-                println!("Key {:?} released and tracked", key);
-            } else {
-                println!("Key {:?} already released, skipping", key);
+        ActionType::WithModifiers => {
+            let modifiers = params.modifiers.as_ref().ok_or("missing modifiers")?;
+            for key in modifiers {
+                string_to_key(key).ok_or_else(|| format!("invalid modifier '{}'", key))?;
+            }
+            let actions = params.actions.as_ref().ok_or("missing actions")?;
+            for (i, inner) in actions.iter().enumerate() {
+                validate_action(inner).map_err(|e| format!("actions[{}]: {}", i, e))?;
             }
-            Ok(())
         },
-        ActionType::KeyCombination => {
-            let keys_vec = params.keys.ok_or("Missing keys parameter for KeyCombination")?;
-            let mut enigo_keys = Vec::new();
-            for key_str in keys_vec {
+        ActionType::RawKey => {
+            let code = params.amount.ok_or("missing keycode (amount)")?;
+            u16::try_from(code).map_err(|_| format!("invalid keycode {}", code))?;
+        },
+        ActionType::SetLayer => {
+            params.text.as_deref().filter(|t| !t.is_empty()).ok_or("missing layer name (text)")?;
+        },
+        ActionType::RunMacro => {
+            params.text.as_deref().filter(|t| !t.is_empty()).ok_or("missing macro id (text)")?;
+        },
+        ActionType::FocusWindow => {
+            params.text.as_deref().filter(|t| !t.is_empty()).ok_or("missing window title (text)")?;
+        },
+        ActionType::WaitForPixel => {
+            params.x.ok_or("missing x")?;
+            params.y.ok_or("missing y")?;
+            parse_hex_color(params.text.as_deref().ok_or("missing color (text)")?)?;
+        },
+        ActionType::ScrollSmooth => {
+            params.amount.ok_or("missing amount")?;
+            parse_scroll_direction(params.direction.as_deref())?;
+        },
+        ActionType::KeepAwake => {
+            params.duration.ok_or("missing duration")?;
+            if params.amount.is_some_and(|interval| interval <= 0) {
+                return Err("interval (amount) must be positive".to_string());
+            }
+        },
+        ActionType::MoveAndClick => {
+            params.x.ok_or("missing x")?;
+            params.y.ok_or("missing y")?;
+            let button = params.button.as_deref().ok_or("missing button")?;
+            string_to_mouse_button(button).ok_or_else(|| format!("invalid mouse button '{}'", button))?;
+        },
+        ActionType::Delay => {
+            if let Some(note_value) = params.note_value.as_deref() {
+                parse_note_value(note_value)?;
+            }
+        },
+        ActionType::DragTo => {
+            params.end_x.ok_or("missing end_x")?;
+            params.end_y.ok_or("missing end_y")?;
+            if params.x.is_some() != params.y.is_some() {
+                return Err("start x and y must be set together".to_string());
+            }
+            parse_easing(params.easing.as_deref())?;
+            let button = params.button.as_deref().ok_or("missing button")?;
+            string_to_mouse_button(button).ok_or_else(|| format!("invalid mouse button '{}'", button))?;
+        },
+        ActionType::MouseRelease | ActionType::MouseDrag => {
+            let button = params.button.as_deref().ok_or("missing button")?;
+            string_to_mouse_button(button).ok_or_else(|| format!("invalid mouse button '{}'", button))?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+// Validate a macro's MIDI trigger fields and all of its actions, returning every problem found
+fn validate_macro(config: &MacroConfig) -> Vec<String> {
+    let phases = [
+        ("before_actions", config.before_actions.as_deref()),
+        ("actions", Some(config.actions.as_slice())),
+        ("increment_actions", config.increment_actions.as_deref()),
+        ("decrement_actions", config.decrement_actions.as_deref()),
+        ("alternate_actions", config.alternate_actions.as_deref()),
+        ("after_actions", config.after_actions.as_deref()),
+    ];
+
+    let mut problems = Vec::new();
+    // parse_midi_message produces channels 1-16 and 7-bit data bytes, so anything
+    // outside those ranges could never match
+    if config.midi_channel > 16 {
+        problems.push(format!("Macro '{}' ({}): midi_channel {} must be 0 (any) or 1-16", config.name, config.id, config.midi_channel));
+    }
+    let data_fields = [
+        ("midi_note", Some(config.midi_note)),
+        ("midi_note_min", config.midi_note_min),
+        ("midi_note_max", config.midi_note_max),
+        ("midi_value", config.midi_value),
+        ("midi_value_min", config.midi_value_min),
+        ("midi_value_max", config.midi_value_max),
+    ];
+    if let (Some(min), Some(max)) = (config.midi_value_min, config.midi_value_max) {
+        if min > max {
+            problems.push(format!("Macro '{}' ({}): midi_value_min {} is above midi_value_max {}", config.name, config.id, min, max));
+        }
+    }
+    if let Some(param) = config.nrpn_param.filter(|p| *p > 0x3FFF) {
+        problems.push(format!("Macro '{}' ({}): nrpn_param {} must be 0-16383", config.name, config.id, param));
+    }
+    match config.schedule {
+        Some(ScheduleSpec::Interval { ms }) if ms < MIN_SCHEDULE_INTERVAL_MS => {
+            problems.push(format!("Macro '{}' ({}): schedule interval must be at least {}ms", config.name, config.id, MIN_SCHEDULE_INTERVAL_MS));
+        },
+        Some(ScheduleSpec::Daily { hour, minute }) if hour > 23 || minute > 59 => {
+            problems.push(format!("Macro '{}' ({}): schedule time {}:{:02} is not a valid time of day", config.name, config.id, hour, minute));
+        },
+        _ => {},
+    }
+    for (field, value) in data_fields {
+        if let Some(value) = value.filter(|v| *v > 127) {
+            problems.push(format!("Macro '{}' ({}): {} {} must be 0-127", config.name, config.id, field, value));
+        }
+    }
+    for (phase, actions) in phases {
+        for (i, action) in actions.unwrap_or_default().iter().enumerate() {
+            if let Err(e) = validate_action(action) {
+                problems.push(format!("Macro '{}' ({}) {}[{}]: {}", config.name, config.id, phase, i, e));
+            }
+        }
+    }
+    problems
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Easing {
+    Linear,
+    EaseInOut,
+}
+
+fn parse_easing(easing: Option<&str>) -> Result<Easing, String> {
+    match easing.map(|e| e.to_lowercase()).as_deref() {
+        None | Some("linear") => Ok(Easing::Linear),
+        Some("ease-in-out") | Some("easeinout") => Ok(Easing::EaseInOut),
+        Some(other) => Err(format!("Invalid easing: {}", other)),
+    }
+}
+
+// Map linear progress t (0..=1) onto the eased progress
+fn apply_easing(easing: Easing, t: f64) -> f64 {
+    match easing {
+        Easing::Linear => t,
+        Easing::EaseInOut => {
+            if t < 0.5 {
+                2.0 * t * t
+            } else {
+                1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+            }
+        },
+    }
+}
+
+// Move the cursor from `from` to `to` in small absolute steps over duration_ms,
+// using the same step sizing as MouseDrag (at least 20 steps, one per ~10ms).
+// Stops where it is if the token is cancelled.
+fn smooth_move_to(enigo: &mut Enigo, from: (i32, i32), to: (i32, i32), duration_ms: u32, easing: Easing, token: &ActionToken) -> Result<(), String> {
+    if duration_ms == 0 {
+        enigo.mouse_move_to(to.0, to.1);
+        return Ok(());
+    }
+
+    let steps = 20.max((duration_ms / 10) as i32);
+    let sleep_duration = std::time::Duration::from_millis((duration_ms as u64) / (steps as u64));
+    for i in 1..=steps {
+        let progress = apply_easing(easing, i as f64 / steps as f64);
+        let x = from.0 + ((to.0 - from.0) as f64 * progress).round() as i32;
+        let y = from.1 + ((to.1 - from.1) as f64 * progress).round() as i32;
+        enigo.mouse_move_to(x, y);
+        if i < steps && sleep_duration > std::time::Duration::from_millis(1) {
+            std::thread::sleep(sleep_duration);
+        }
+        token.check()?;
+    }
+    Ok(())
+}
+
+// Shared between a queued action and whoever waits for it. The waiter can cancel the action
+// before it starts, and the action reports when it does start, so a timeout only counts the
+// time it actually runs and not the time spent queued behind other input.
+struct ActionToken {
+    cancelled: std::sync::atomic::AtomicBool,
+    started: tokio::sync::Notify,
+    // Where the cursor was when a mouse move started, for undo_last_macro to move it back
+    cursor_before: Mutex<Option<(i32, i32)>>,
+}
+
+impl ActionToken {
+    fn new() -> Arc<Self> {
+        Arc::new(ActionToken {
+            cancelled: std::sync::atomic::AtomicBool::new(false),
+            started: tokio::sync::Notify::new(),
+            cursor_before: Mutex::new(None),
+        })
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn mark_started(&self) {
+        self.started.notify_one();
+    }
+
+    fn record_cursor(&self, position: (i32, i32)) {
+        *lock_or_recover(&self.cursor_before) = Some(position);
+    }
+
+    fn cursor_before(&self) -> Option<(i32, i32)> {
+        *lock_or_recover(&self.cursor_before)
+    }
+
+    // For the steps of long blocking actions (animated moves, drags, delays), so a timed-out
+    // action stops at its next step instead of moving the mouse after the flow moved on
+    fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err("Action was cancelled".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Command to execute an action based on a macro
+#[tauri::command]
+async fn execute_action<R: Runtime>(app_handle: AppHandle<R>, action_type: ActionType, params: ActionParams) -> Result<(), String> {
+    execute_action_safe(action_type, params, None, ActionToken::new(), Some(app_handle)).await
+}
+
+// Internal implementation that can be called from different contexts.
+// `owner` is the macro id recorded for any key or button this action leaves held.
+#[cfg(target_os = "macos")]
+fn execute_action_impl(action_type: ActionType, params: ActionParams, owner: Option<String>, token: &ActionToken) -> Result<(), String> {
+    // Create a new Enigo instance for each action execution
+    log::debug!("Creating new Enigo instance for action: {:?}", action_type);
+    let mut enigo = create_enigo();
+    execute_action_with(&mut enigo, action_type, params, owner, token)
+}
+
+// Execute one action with the given Enigo (see execute_action_impl and the input threads)
+fn execute_action_with(enigo: &mut Enigo, action_type: ActionType, params: ActionParams, owner: Option<String>, token: &ActionToken) -> Result<(), String> {
+    match action_type {
+        ActionType::MouseMove => {
+            let x = params.x.ok_or("Missing x parameter for MouseMove")?;
+            let y = params.y.ok_or("Missing y parameter for MouseMove")?;
+            let relative = params.relative.unwrap_or(false);
+            // Some remote-desktop setups drop instant jumps, so moves can be animated by default
+            let duration_ms = params.duration
+                .unwrap_or_else(|| lock_or_recover(&APP_STATE.global_settings).default_move_duration_ms);
+            log::debug!("Executing MouseMove: x={}, y={}, relative={}, duration={}ms", x, y, relative, duration_ms);
+            token.record_cursor(enigo.mouse_location());
+            if duration_ms > 0 {
+                let from = enigo.mouse_location();
+                let to = if relative { (from.0 + x, from.1 + y) } else { (x, y) };
+                smooth_move_to(enigo, from, to, duration_ms, Easing::Linear, token)?;
+            } else if relative {
+                enigo.mouse_move_relative(x, y);
+            } else {
+                enigo.mouse_move_to(x, y);
+            }
+            log::debug!("MouseMove completed successfully");
+            Ok(())
+        },
+        ActionType::MouseClick => {
+            let button_str = params.button.ok_or("Missing button parameter")?;
+            
+            // Handle scroll actions
+            if button_str == "scroll-up" || button_str == "scroll-down" {
+                let amount = params.amount.unwrap_or(DEFAULT_SCROLL_CLICK_AMOUNT);
+                let scroll_amount = if button_str == "scroll-up" { -amount } else { amount };
+                
+                log::debug!("Executing mouse scroll: direction={}, amount={}", button_str, scroll_amount);
+                enigo.mouse_scroll_y(scroll_amount);
+                log::debug!("Mouse scroll completed successfully");
+                return Ok(());
+            }
+            if button_str == "scroll-left" || button_str == "scroll-right" {
+                let amount = params.amount.unwrap_or(DEFAULT_SCROLL_CLICK_AMOUNT);
+                let scroll_amount = if button_str == "scroll-left" { -amount } else { amount };
+                
+                log::debug!("Executing horizontal mouse scroll: direction={}, amount={}", button_str, scroll_amount);
+                enigo.mouse_scroll_x(scroll_amount);
+                log::debug!("Horizontal mouse scroll completed successfully");
+                return Ok(());
+            }
+            
+            // Side buttons on macOS can only be clicked, by number
+            #[cfg(target_os = "macos")]
+            if let Some(number) = side_button_number(&button_str) {
+                if params.hold == Some(true) {
+                    return Err(format!("Holding the {} button is not supported on macOS", button_str));
+                }
+                for click in 0..params.amount.unwrap_or(1).max(1) {
+                    if click > 0 {
+                        std::thread::sleep(std::time::Duration::from_millis(MULTI_CLICK_INTERVAL_MS));
+                    }
+                    click_mouse_button_number(number)?;
+                }
+                return Ok(());
+            }
+            
+            // Handle regular mouse clicks
+            let button = string_to_mouse_button(&button_str)
+                .ok_or_else(|| format!("Invalid mouse button: {}", button_str))?;
+            
+            if params.hold == Some(true) {
+                let mut mouse_state = lock_or_recover(&APP_STATE.mouse_state);
+                if let Entry::Vacant(entry) = mouse_state.entry(button) {
+                    enigo.mouse_down(button);
+                    entry.insert(owner);
+                    log::debug!("Mouse {:?} pressed and tracked", button);
+                } else {
+                    log::debug!("Mouse {:?} already pressed, skipping", button);
+                }
+            } else {
+                // amount = click count
+                click_n(enigo, button, params.amount.unwrap_or(1));
+            }
+            Ok(())
+        },
+        ActionType::MoveAndClick => {
+            let x = params.x.ok_or("Missing x parameter for MoveAndClick")?;
+            let y = params.y.ok_or("Missing y parameter for MoveAndClick")?;
+            let button_str = params.button.ok_or("Missing button parameter for MoveAndClick")?;
+            let button = string_to_mouse_button(&button_str)
+                .ok_or_else(|| format!("Invalid mouse button for MoveAndClick: {}", button_str))?;
+            let relative = params.relative.unwrap_or(false);
+
+            log::debug!("Executing MoveAndClick: x={}, y={}, relative={}, button={:?}", x, y, relative, button);
+            token.record_cursor(enigo.mouse_location());
+            if relative {
+                enigo.mouse_move_relative(x, y);
+            } else {
+                enigo.mouse_move_to(x, y);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(MOVE_SETTLE_MS));
+
+            // Same as a regular MouseClick: amount = click count
+            click_n(enigo, button, params.amount.unwrap_or(1));
+            log::debug!("MoveAndClick completed successfully");
+            Ok(())
+        },
+        ActionType::KeyPress => {
+            let key_str = params.key.ok_or("Missing key parameter for KeyPress")?;
+            let key = string_to_key(&key_str)
+                .ok_or_else(|| format!("Invalid key: {}", key_str))?;
+            
+            if params.hold == Some(true) {
+                let mut key_state = lock_or_recover(&APP_STATE.key_state);
+                if let Entry::Vacant(entry) = key_state.entry(key) {
+                    log::debug!("Executing KeyPress with hold: key={:?}", key);
+                    enigo.key_down(key);
+                    entry.insert(owner);
+                    log::debug!("Key {:?} pressed and held, tracked in state", key);
+                } else {
+                    log::debug!("Key {:?} already held, skipping", key);
+                }
+            } else {
+                // For regular key presses, always release first to be safe
+                let mut key_state = lock_or_recover(&APP_STATE.key_state);
+                if key_state.remove(&key).is_some() {
+                    enigo.key_up(key);
+                }
+            log::debug!("Executing KeyPress: key={:?}", key);
+            enigo.key_click(key);
+            log::debug!("KeyPress completed successfully");
+            }
+            Ok(())
+        },
+        ActionType::KeyTap => {
+            // A single tap; repetition and spacing are handled asynchronously by run_actions
+            let key_str = params.key.ok_or("Missing key parameter for KeyTap")?;
+            let key = string_to_key(&key_str)
+                .ok_or_else(|| format!("Invalid key: {}", key_str))?;
+
+            let mut key_state = lock_or_recover(&APP_STATE.key_state);
+            if key_state.remove(&key).is_some() {
+                enigo.key_up(key);
+            }
+            log::debug!("Executing KeyTap: key={:?}", key);
+            enigo.key_click(key);
+            Ok(())
+        },
+        ActionType::RawKey => {
+            // Escape hatch for keys string_to_key can't name; the same code means
+            // different keys on Windows, macOS and Linux, so macros using it aren't portable
+            let code = params.amount.ok_or("Missing amount (keycode) parameter for RawKey")?;
+            let code = u16::try_from(code).map_err(|_| format!("Invalid keycode: {}", code))?;
+            log::warn!("RawKey sends platform-specific keycode {} as-is", code);
+            enigo.key_click(Key::Raw(code));
+            Ok(())
+        },
+        ActionType::KeyRelease => {
+            let key_str = params.key.ok_or("Missing key parameter for KeyRelease")?;
+            let key = string_to_key(&key_str)
+                .ok_or_else(|| format!("Invalid key: {}", key_str))?;
+            
+            let mut key_state = lock_or_recover(&APP_STATE.key_state);
+            if key_state.remove(&key).is_some() {
+                log::debug!("Executing KeyRelease: key={:?}", key);
+                enigo.key_up(key);
+                log::debug!("Key {:?} released and tracked", key);
+            } else {
+                log::debug!("Key {:?} already released, skipping", key);
+            }
+            Ok(())
+        },
+        ActionType::KeyCombination => {
+            let keys_vec = params.keys.ok_or("Missing keys parameter for KeyCombination")?;
+            let mut enigo_keys = Vec::new();
+            for key_str in keys_vec {
                 let enigo_key = string_to_key(&key_str)
                     .ok_or_else(|| format!("Invalid key in combination: {}", key_str))?;
                 enigo_keys.push(enigo_key);
             }
-            println!("Executing KeyCombination: keys={:?}", enigo_keys);
+            log::debug!("Executing KeyCombination: keys={:?}", enigo_keys);
             for key in &enigo_keys {
                 enigo.key_down(*key);
             }
             for key in enigo_keys.iter().rev() {
                 enigo.key_up(*key);
             }
-            println!("KeyCombination completed successfully");
+            log::debug!("KeyCombination completed successfully");
             Ok(())
         },
         ActionType::MouseRelease => {
@@ -374,13 +1527,12 @@ fn execute_action_impl(action_type: ActionType, params: ActionParams) -> Result<
             let button = string_to_mouse_button(&button_str)
                 .ok_or_else(|| format!("Invalid mouse button: {}", button_str))?;
             
-            let mut mouse_state = APP_STATE.mouse_state.lock().unwrap();
-            if *mouse_state.get(&button).unwrap_or(&false) {
+            let mut mouse_state = lock_or_recover(&APP_STATE.mouse_state);
+            if mouse_state.remove(&button).is_some() {
                 enigo.mouse_up(button);
-                mouse_state.insert(button, false);
-                println!("Mouse {:?} released and tracked", button);
+                log::debug!("Mouse {:?} released and tracked", button);
             } else {
-                println!("Mouse {:?} already released, skipping", button);
+                log::debug!("Mouse {:?} already released, skipping", button);
             }
             Ok(())
         },
@@ -408,6 +1560,10 @@ fn execute_action_impl(action_type: ActionType, params: ActionParams) -> Result<
                             std::thread::sleep(sleep_duration);
                          }
                     }
+                    if let Err(e) = token.check() {
+                        enigo.mouse_up(button);
+                        return Err(e);
+                    }
                 }
             } else {
                 // Instantaneous move if duration is 0
@@ -417,122 +1573,978 @@ fn execute_action_impl(action_type: ActionType, params: ActionParams) -> Result<
             enigo.mouse_up(button);
             Ok(())
         },
+        ActionType::TypeText => {
+            let text = params.text.ok_or("Missing text parameter for TypeText")?;
+            log::debug!("Executing TypeText: {} chars", text.chars().count());
+
+            // key_sequence types each character as a full press/release, so nothing typed here
+            // is recorded in key_state and no KeyRelease is needed afterwards. Held keys from
+            // earlier KeyPress actions stay held (e.g. a held Shift still affects the typed text).
+            // Newlines and tabs are sent as explicit Return/Tab clicks for consistency across platforms.
+            let mut chunk = String::new();
+            for c in text.chars() {
+                let key = match c {
+                    '\n' => Key::Return,
+                    '\t' => Key::Tab,
+                    '\r' => continue,
+                    _ => {
+                        chunk.push(c);
+                        continue;
+                    }
+                };
+                if !chunk.is_empty() {
+                    enigo.key_sequence(&chunk);
+                    chunk.clear();
+                }
+                enigo.key_click(key);
+            }
+            if !chunk.is_empty() {
+                enigo.key_sequence(&chunk);
+            }
+            log::debug!("TypeText completed successfully");
+            Ok(())
+        },
+        ActionType::DragTo => {
+            let button_str = params.button.ok_or("Missing button parameter for DragTo")?;
+            let button = string_to_mouse_button(&button_str)
+                .ok_or_else(|| format!("Invalid mouse button for DragTo: {}", button_str))?;
+            let end_x = params.end_x.ok_or("Missing end_x parameter for DragTo")?;
+            let end_y = params.end_y.ok_or("Missing end_y parameter for DragTo")?;
+            let duration_ms = params.duration.unwrap_or(0);
+            let easing = parse_easing(params.easing.as_deref())?;
+
+            let from = match (params.x, params.y) {
+                (Some(x), Some(y)) => {
+                    enigo.mouse_move_to(x, y);
+                    std::thread::sleep(std::time::Duration::from_millis(MOVE_SETTLE_MS));
+                    (x, y)
+                },
+                _ => enigo.mouse_location(),
+            };
+            log::debug!("Executing DragTo: button={:?}, from={:?}, to=({}, {}), duration={}ms", button, from, end_x, end_y, duration_ms);
+            enigo.mouse_down(button);
+            let moved = smooth_move_to(enigo, from, (end_x, end_y), duration_ms, easing, token);
+            enigo.mouse_up(button);
+            moved
+        },
+        ActionType::MouseMoveSmooth => {
+            let x = params.x.ok_or("Missing x parameter for MouseMoveSmooth")?;
+            let y = params.y.ok_or("Missing y parameter for MouseMoveSmooth")?;
+            let relative = params.relative.unwrap_or(false);
+            let duration_ms = params.duration.unwrap_or(0);
+            let easing = parse_easing(params.easing.as_deref())?;
+
+            let from = enigo.mouse_location();
+            token.record_cursor(from);
+            let to = if relative { (from.0 + x, from.1 + y) } else { (x, y) };
+            log::debug!("Executing MouseMoveSmooth: from={:?}, to={:?}, duration={}ms, easing={:?}", from, to, duration_ms, easing);
+            smooth_move_to(enigo, from, to, duration_ms, easing, token)?;
+            log::debug!("MouseMoveSmooth completed successfully");
+            Ok(())
+        },
+        ActionType::ScrollSmooth => {
+            // A single scroll step; splitting and spacing are handled asynchronously by run_actions
+            let amount = params.amount.ok_or("Missing amount parameter for ScrollSmooth")?;
+            let (horizontal, sign) = parse_scroll_direction(params.direction.as_deref())?;
+            log::debug!("Executing ScrollSmooth step: amount={}, horizontal={}", amount * sign, horizontal);
+            if horizontal {
+                enigo.mouse_scroll_x(amount * sign);
+            } else {
+                enigo.mouse_scroll_y(amount * sign);
+            }
+            Ok(())
+        },
+        ActionType::KeepAwake => {
+            // A single nudge; the interval and total duration are handled asynchronously by run_actions
+            log::debug!("Executing KeepAwake nudge");
+            enigo.mouse_move_relative(1, 0);
+            enigo.mouse_move_relative(-1, 0);
+            Ok(())
+        },
+        ActionType::SendMidi | ActionType::SetLayer | ActionType::LatchModifier => {
+            Err(format!("{:?} is handled by execute_action_safe and needs no Enigo instance", action_type))
+        },
+        ActionType::WithModifiers | ActionType::RunMacro => {
+            Err(format!("{:?} runs nested actions and must be executed through run_actions", action_type))
+        },
+        ActionType::WaitForPixel => {
+            Err("WaitForPixel polls the screen and must be executed through run_actions".to_string())
+        },
+        ActionType::KeyHoldFor => {
+            Err("KeyHoldFor waits between press and release and must be executed through run_actions".to_string())
+        },
+        ActionType::FocusWindow => {
+            let title = params.text.ok_or("Missing text (window title) parameter for FocusWindow")?;
+            log::debug!("Executing FocusWindow: {}", title);
+            focus_window(&title)
+        },
         ActionType::Delay => {
-            // This shouldn't be reached if called from the new async loops
-            println!("**************************************************************************");
-            println!("ERROR: execute_action was called with ActionType::Delay - this is wrong!");
-            println!("Delay should be handled by the async loop, not by execute_action.");
-            println!("Params: {:?}", params);
-            println!("**************************************************************************");
-            Err("Delay action type should be handled by the calling async loop".to_string())
+            // run_actions awaits delays without blocking; this fallback only serves direct
+            // single-action calls such as execute_action
+            let delay = delay_duration(&params).unwrap_or_default();
+            log::debug!("Executing Delay outside run_actions: sleeping {:?} on the calling thread", delay);
+            let deadline = std::time::Instant::now() + delay;
+            loop {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                if remaining.is_zero() {
+                    return Ok(());
+                }
+                std::thread::sleep(remaining.min(std::time::Duration::from_millis(CANCEL_CHECK_INTERVAL_MS)));
+                token.check()?;
+            }
         },
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+struct DryRunActionEvent {
+    action_type: ActionType,
+    action_params: ActionParams,
+    timestamp_ms: u64,
+}
+
+// Entry point for running a single action. In dry-run mode the action is only
+// reported to the frontend through a dry-run-action event and never executed.
+async fn execute_action_safe<R: Runtime>(
+    action_type: ActionType,
+    params: ActionParams,
+    owner: Option<String>,
+    token: Arc<ActionToken>,
+    app_handle: Option<tauri::AppHandle<R>>,
+) -> Result<(), String> {
+    if lock_or_recover(&APP_STATE.global_settings).dry_run {
+        log::info!("Dry run: {:?} {:?}", action_type, params);
+        if let Some(app) = &app_handle {
+            let timestamp_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let _ = app.emit("dry-run-action", DryRunActionEvent {
+                action_type,
+                action_params: params,
+                timestamp_ms,
+            });
+        }
+        return Ok(());
+    }
+    match action_type {
+        ActionType::SendMidi => return send_midi_message(&params),
+        ActionType::SetLayer => {
+            let layer = params.text.ok_or("Missing text (layer name) parameter for SetLayer")?;
+            switch_layer(layer, app_handle.as_ref());
+            return Ok(());
+        },
+        ActionType::LatchModifier => {
+            let key = params.key.ok_or("Missing key parameter for LatchModifier")?;
+            toggle_latched_modifier(key);
+            return Ok(());
+        },
+        _ => {},
+    }
+    let params = match &app_handle {
+        Some(app) => {
+            let params = resolve_window_coordinates(&action_type, params).await?;
+            resolve_monitor_coordinates(app, &action_type, params)?
+        },
+        None => params,
+    };
+    check_forbidden_regions(app_handle.as_ref(), &action_type, &params, owner.as_deref()).await?;
+    execute_action_on_platform(action_type, params, owner, token, app_handle).await
+}
+
 // Helper function to execute actions safely on macOS (on main thread)
 #[cfg(target_os = "macos")]
-async fn execute_action_safe<R: Runtime>(action_type: ActionType, params: ActionParams, app_handle: Option<tauri::AppHandle<R>>) -> Result<(), String> {
+async fn execute_action_on_platform<R: Runtime>(
+    action_type: ActionType,
+    params: ActionParams,
+    owner: Option<String>,
+    token: Arc<ActionToken>,
+    app_handle: Option<tauri::AppHandle<R>>,
+) -> Result<(), String> {
     if let Some(app) = app_handle {
-        println!("Executing action on macOS: {:?}", action_type);
+        log::debug!("Executing action on macOS: {:?}", action_type);
         let action_type_clone = action_type.clone();
         let params_clone = params.clone();
         
         // Use a channel to get the result back from the main thread
         let (tx, rx) = tokio::sync::oneshot::channel();
         
-        println!("Running action on main thread...");
+        log::debug!("Running action on main thread...");
         app.run_on_main_thread(move || {
-            println!("Inside main thread, executing action...");
-            let result = execute_action_impl(action_type_clone, params_clone);
-            println!("Action execution result: {:?}", result);
+            log::debug!("Inside main thread, executing action...");
+            token.mark_started();
+            let result = if token.is_cancelled() {
+                Err("Action was cancelled before it started".to_string())
+            } else {
+                execute_action_impl(action_type_clone, params_clone, owner, &token)
+            };
+            log::debug!("Action execution result: {:?}", result);
             let _ = tx.send(result);
         }).map_err(|e| format!("Failed to run on main thread: {}", e))?;
         
-        println!("Waiting for action result...");
+        log::debug!("Waiting for action result...");
         let result = rx.await.map_err(|e| format!("Failed to receive result: {}", e))?;
-        println!("Action completed with result: {:?}", result);
+        log::debug!("Action completed with result: {:?}", result);
         result
     } else {
-        println!("Error: No app handle available for macOS UI automation");
+        log::error!("No app handle available for macOS UI automation");
         Err("App handle is required for UI automation on macOS".to_string())
     }
 }
 
-// For non-macOS platforms, just call the implementation directly
+// Actions need the main thread and an AppHandle on macOS, so the input helpers below
+// (releases, cursor reads) keep using a fresh Enigo on the calling thread there
+#[cfg(target_os = "macos")]
+fn queue_input(_owner: Option<&str>, work: impl FnOnce(&mut Enigo) + Send + 'static) {
+    work(&mut create_enigo());
+}
+
+#[cfg(target_os = "macos")]
+async fn query_input<T: Send + 'static>(_owner: Option<&str>, work: impl FnOnce(&mut Enigo) -> T + Send + 'static) -> Result<T, String> {
+    Ok(work(&mut create_enigo()))
+}
+
+// --- Input threads (Windows/Linux) ------------------------------------------------
+// Creating an Enigo opens an X11/uinput connection on Linux, which is slow and can fail
+// under load when done per action. Instead OS threads own long-lived Enigos and input is
+// queued to them, mirroring the macOS main-thread path. Each macro gets its own thread
+// (keyed by owner; None for input outside macros), so a long action of one macro doesn't
+// hold up the others, and everything touching a macro's input - its actions, releasing
+// what it holds, reading the cursor it moved - runs there in the order it was queued.
+#[cfg(not(target_os = "macos"))]
+type InputWork = Box<dyn FnOnce(&mut Enigo) + Send>;
+
 #[cfg(not(target_os = "macos"))]
-async fn execute_action_safe<R: Runtime>(action_type: ActionType, params: ActionParams, _app_handle: Option<tauri::AppHandle<R>>) -> Result<(), String> {
-    execute_action_impl(action_type, params)
+struct InputThread {
+    sender: std::sync::mpsc::Sender<InputWork>,
+    // Set when the thread is replaced; it then drops what is still queued instead of running it
+    retired: Arc<std::sync::atomic::AtomicBool>,
 }
 
-// Command to register a MIDI macro
-#[tauri::command]
-fn register_macro(config: MacroConfig) -> Result<(), String> {
-    // Just log for now - in a real app, you'd store this in a database or config file
-    println!("Attempting to register macro: {:?}", config);
-    
-    // Check if macro is already registered and if it has an active task running
-    {
-        let mut active_macros = APP_STATE.active_macros.lock().unwrap();
-        if let Some(active_macro) = active_macros.remove(&config.id) {
-            // Abort any pending after_actions task
-            active_macro.abort_handle.abort();
-            println!("Aborted pending after_actions for macro {}.", config.id);
-        }
+#[cfg(not(target_os = "macos"))]
+static INPUT_THREADS: Lazy<Mutex<HashMap<Option<String>, InputThread>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// An input thread with nothing to do for this long exits, closing its Enigo
+#[cfg(not(target_os = "macos"))]
+const INPUT_THREAD_IDLE_MS: u64 = 30_000;
+
+#[cfg(not(target_os = "macos"))]
+fn spawn_input_thread(owner: Option<String>) -> Result<InputThread, String> {
+    let (tx, rx) = std::sync::mpsc::channel::<InputWork>();
+    let retired = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let thread_retired = retired.clone();
+    let name = owner.as_deref().unwrap_or("direct").to_string();
+    log::info!("Starting input thread for {}", owner.as_deref().unwrap_or("direct input"));
+    std::thread::Builder::new()
+        .name(format!("opengrader-input-{}", name))
+        .spawn(move || {
+            let mut enigo: Option<Enigo> = None;
+            let idle = std::time::Duration::from_millis(INPUT_THREAD_IDLE_MS);
+            loop {
+                match rx.recv_timeout(idle) {
+                    Ok(work) => run_input_work(&mut enigo, work, &thread_retired),
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                        // Work is only queued under the INPUT_THREADS lock, so once unregistered
+                        // nothing new arrives; finish whatever slipped in before that
+                        unregister_input_thread(&owner, &thread_retired);
+                        for work in rx.try_iter() {
+                            run_input_work(&mut enigo, work, &thread_retired);
+                        }
+                        break;
+                    },
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            log::debug!("Input thread for {} stopped", name);
+        })
+        .map_err(|e| format!("Failed to start input thread: {}", e))?;
+    Ok(InputThread { sender: tx, retired })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn run_input_work(enigo: &mut Option<Enigo>, work: InputWork, retired: &std::sync::atomic::AtomicBool) {
+    if retired.load(std::sync::atomic::Ordering::Relaxed) {
+        // Dropping the work tells its waiter it never ran
+        log::debug!("Dropping input queued on a replaced input thread");
+        return;
     }
-    
-    let mut macros = APP_STATE.registered_macros.lock().unwrap();
-    // Optional: Prevent duplicate registration by ID or name if desired
-    if macros.iter().any(|m| m.id == config.id) {
-        println!("Macro with ID {} already registered. Updating.", config.id);
-        macros.retain(|m| m.id != config.id);
+    let current = enigo.get_or_insert_with(create_enigo);
+    if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| work(current))).is_err() {
+        // Reconnect for the next job in case the panic left this Enigo unusable
+        log::error!("Input thread panicked while executing queued input");
+        *enigo = None;
     }
-    macros.push(config.clone()); // Store the macro
-    println!("Successfully registered macro. Total macros: {}", macros.len());
-    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unregister_input_thread(owner: &Option<String>, retired: &Arc<std::sync::atomic::AtomicBool>) {
+    let mut threads = lock_or_recover(&INPUT_THREADS);
+    if threads.get(owner).is_some_and(|thread| Arc::ptr_eq(&thread.retired, retired)) {
+        threads.remove(owner);
+    }
+}
+
+// Queue work on the owner's input thread, starting it on first use or if it has gone away
+#[cfg(not(target_os = "macos"))]
+fn send_input_work(owner: Option<&str>, work: InputWork) -> Result<(), String> {
+    let owner = owner.map(str::to_string);
+    let mut threads = lock_or_recover(&INPUT_THREADS);
+    let work = match threads.get(&owner) {
+        Some(thread) => match thread.sender.send(work) {
+            Ok(()) => return Ok(()),
+            Err(std::sync::mpsc::SendError(work)) => work,
+        },
+        None => work,
+    };
+    let thread = spawn_input_thread(owner.clone())?;
+    thread.sender.send(work).map_err(|_| "Input thread stopped unexpectedly".to_string())?;
+    threads.insert(owner, thread);
+    Ok(())
+}
+
+// Run `work` on the owner's input thread without waiting for it
+#[cfg(not(target_os = "macos"))]
+fn queue_input(owner: Option<&str>, work: impl FnOnce(&mut Enigo) + Send + 'static) {
+    if let Err(e) = send_input_work(owner, Box::new(work)) {
+        log::error!("Failed to queue input for {}: {}", owner.unwrap_or("direct input"), e);
+    }
+}
+
+// Run `work` on the owner's input thread and wait for its result
+#[cfg(not(target_os = "macos"))]
+async fn query_input<T: Send + 'static>(owner: Option<&str>, work: impl FnOnce(&mut Enigo) -> T + Send + 'static) -> Result<T, String> {
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    send_input_work(owner, Box::new(move |enigo| {
+        let _ = result_tx.send(work(enigo));
+    }))?;
+    result_rx.await.map_err(|_| "Input thread stopped before finishing the action".to_string())
+}
+
+// Hand the owner's new input to a fresh thread, e.g. when the current one is stuck on an
+// action that timed out. Input still queued on the old thread is dropped, so it can't
+// press anything after the caller has released what the macro holds.
+#[cfg(not(target_os = "macos"))]
+fn restart_input_thread(owner: Option<&str>) {
+    let owner = owner.map(str::to_string);
+    if let Some(thread) = lock_or_recover(&INPUT_THREADS).remove(&owner) {
+        thread.retired.store(true, std::sync::atomic::Ordering::Relaxed);
+        log::warn!(
+            "Replacing the input thread for {}; the previous one exits once its current action returns",
+            owner.as_deref().unwrap_or("direct input")
+        );
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+async fn execute_action_on_platform<R: Runtime>(
+    action_type: ActionType,
+    params: ActionParams,
+    owner: Option<String>,
+    token: Arc<ActionToken>,
+    _app_handle: Option<tauri::AppHandle<R>>,
+) -> Result<(), String> {
+    let thread = owner.clone();
+    query_input(thread.as_deref(), move |enigo| {
+        token.mark_started();
+        if token.is_cancelled() {
+            return Err("Action was cancelled before it started".to_string());
+        }
+        execute_action_with(enigo, action_type, params, owner, &token)
+    }).await?
+}
+
+// --- Macro persistence ---------------------------------------------------------
+const MACROS_FILE_NAME: &str = "macros.json";
+
+fn macros_file_path() -> Option<std::path::PathBuf> {
+    lock_or_recover(&APP_STATE.config_dir).as_ref().map(|dir| dir.join(MACROS_FILE_NAME))
+}
+
+// --- Last-connected MIDI port ---------------------------------------------------
+const MIDI_PORT_FILE_NAME: &str = "midi_port.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct MidiPortMemory {
+    last_port_name: Option<String>,
+}
+
+fn midi_port_file_path() -> Option<std::path::PathBuf> {
+    lock_or_recover(&APP_STATE.config_dir).as_ref().map(|dir| dir.join(MIDI_PORT_FILE_NAME))
+}
+
+fn load_midi_port_memory() -> Option<MidiPortMemory> {
+    let path = midi_port_file_path()?;
+    let json = std::fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&json)
+        .map_err(|e| log::warn!("Failed to parse {:?}: {}", path, e))
+        .ok()
+}
+
+fn remember_midi_port(port_name: &str) {
+    let Some(path) = midi_port_file_path() else { return };
+    let memory = MidiPortMemory { last_port_name: Some(port_name.to_string()) };
+
+    let result = serde_json::to_string_pretty(&memory)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log::error!("Failed to save {:?}: {}", path, e);
+    }
+}
+
+fn save_macros_to_disk(macros: &[MacroConfig]) -> Result<(), String> {
+    let path = macros_file_path().ok_or("Config directory is not initialized")?;
+    write_macros_file(&path, macros)
+}
+
+// Write the macros to a temp file first and rename it over the real one,
+// so a crash mid-save never leaves a truncated config behind
+fn write_macros_file(path: &std::path::Path, macros: &[MacroConfig]) -> Result<(), String> {
+    use std::io::Write;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory {:?}: {}", dir, e))?;
+    }
+
+    let json = serde_json::to_string_pretty(macros)
+        .map_err(|e| format!("Failed to serialize macros: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut file = std::fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create {:?}: {}", tmp_path, e))?;
+        file.write_all(json.as_bytes())
+            .map_err(|e| format!("Failed to write {:?}: {}", tmp_path, e))?;
+        file.sync_all()
+            .map_err(|e| format!("Failed to flush {:?}: {}", tmp_path, e))?;
+    }
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {:?}: {}", path, e))?;
+
+    log::info!("Saved {} macros to {:?}", macros.len(), path);
+    Ok(())
+}
+
+fn load_macros_from_disk() -> Result<Vec<MacroConfig>, String> {
+    let path = macros_file_path().ok_or("Config directory is not initialized")?;
+    read_macros_file(&path)
+}
+
+// Read and migrate a saved macro set; a missing file is an empty set
+fn read_macros_file(path: &std::path::Path) -> Result<Vec<MacroConfig>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let mut macros: Vec<MacroConfig> = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+    macros.iter_mut().for_each(migrate_macro_config);
+    Ok(macros)
+}
+
+// Persist the current macro set, logging instead of failing the calling command
+fn persist_macros(macros: &[MacroConfig]) {
+    if let Err(e) = save_macros_to_disk(macros) {
+        log::error!("Failed to persist macros: {}", e);
+    }
+}
+
+// Command to load persisted macros into the registered set (also run on startup)
+#[tauri::command]
+fn load_macros() -> Result<Vec<MacroConfig>, String> {
+    let loaded = load_macros_from_disk()?;
+    let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+    *macros = loaded;
+    log::info!("Loaded {} macros from disk", macros.len());
+    Ok(macros.clone())
+}
+
+// Command to register a MIDI macro
+#[tauri::command]
+fn register_macro(config: MacroConfig) -> Result<(), String> {
+    // Just log for now - in a real app, you'd store this in a database or config file
+    log::info!("Attempting to register macro: {:?}", config);
+    
+    // Reject bad key/button names now rather than failing silently at trigger time
+    let problems = validate_macro(&config);
+    if !problems.is_empty() {
+        log::warn!("Rejected macro {}: {:?}", config.id, problems);
+        return Err(format!("Invalid macro configuration:\n{}", problems.join("\n")));
+    }
+    
+    reset_macro_runtime_state(&config.id);
+    
+    let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+    insert_macro(&mut macros, config);
+    log::info!("Successfully registered macro. Total macros: {}", macros.len());
+    persist_macros(&macros);
+    Ok(())
+}
+
+// Abort a (re-)registered macro's pending after_actions and reset its toggle state
+fn reset_macro_runtime_state(id: &str) {
+    if let Some(active_macro) = lock_or_recover(&APP_STATE.active_macros).remove(id) {
+        // Abort any pending after_actions task
+        active_macro.abort_handle.abort();
+        log::info!("Aborted pending after_actions for macro {}.", id);
+    }
+    
+    // A re-registered toggle macro starts over in its "off" state
+    lock_or_recover(&APP_STATE.macro_toggle_states).remove(id);
+    lock_or_recover(&APP_STATE.held_momentary_macros).remove(id);
+}
+
+// Current MacroConfig schema version, written with every saved or exported macro
+const MACRO_CONFIG_VERSION: u32 = 2;
+
+// Upgrade a config read from an older schema in place. Fields added since then are
+// already filled in by serde defaults; steps that change what an existing field means
+// go here, one per version, so any old config can be brought up to date.
+fn migrate_macro_config(config: &mut MacroConfig) {
+    if config.config_version > MACRO_CONFIG_VERSION {
+        log::warn!(
+            "Macro {} was written by a newer version (schema {}, this build supports {}); unknown fields are ignored",
+            config.id, config.config_version, MACRO_CONFIG_VERSION
+        );
+        return;
+    }
+    if config.config_version < MACRO_CONFIG_VERSION {
+        log::debug!("Migrating macro {} from schema {} to {}", config.id, config.config_version, MACRO_CONFIG_VERSION);
+    }
+    // 0 -> 1: versioning introduced; every field kept its meaning
+    // 1 -> 2: MouseMove honours `duration`. The editor used to save 500 on every move, which
+    // the backend ignored, so drop it and let default_move_duration_ms decide instead
+    if config.config_version < 2 {
+        let lists = std::iter::once(&mut config.actions)
+            .chain(config.before_actions.iter_mut())
+            .chain(config.after_actions.iter_mut())
+            .chain(config.increment_actions.iter_mut())
+            .chain(config.decrement_actions.iter_mut())
+            .chain(config.alternate_actions.iter_mut());
+        for action in lists.flatten() {
+            if matches!(action.action_type, ActionType::MouseMove) {
+                action.action_params.duration = None;
+            }
+        }
+    }
+    config.config_version = MACRO_CONFIG_VERSION;
+}
+
+// Store a macro, replacing any registered macro with the same id
+fn insert_macro(macros: &mut Vec<MacroConfig>, mut config: MacroConfig) {
+    migrate_macro_config(&mut config);
+    if macros.iter().any(|m| m.id == config.id) {
+        log::debug!("Macro with ID {} already registered. Updating.", config.id);
+        macros.retain(|m| m.id != config.id);
+    }
+    macros.push(config);
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MacroRegistrationResult {
+    id: String,
+    error: Option<String>, // None if the macro was registered
+}
+
+// Command to register many macros at once, e.g. on startup. Valid macros are stored
+// under a single lock and saved once; invalid ones are skipped and reported per id.
+#[tauri::command]
+fn register_macros(configs: Vec<MacroConfig>) -> Vec<MacroRegistrationResult> {
+    let mut results = Vec::with_capacity(configs.len());
+    let mut valid = Vec::with_capacity(configs.len());
+    for config in configs {
+        let problems = validate_macro(&config);
+        if problems.is_empty() {
+            reset_macro_runtime_state(&config.id);
+            results.push(MacroRegistrationResult { id: config.id.clone(), error: None });
+            valid.push(config);
+        } else {
+            log::warn!("Rejected macro {}: {:?}", config.id, problems);
+            results.push(MacroRegistrationResult {
+                id: config.id,
+                error: Some(format!("Invalid macro configuration:\n{}", problems.join("\n"))),
+            });
+        }
+    }
+
+    if !valid.is_empty() {
+        let registered = valid.len();
+        let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+        for config in valid {
+            insert_macro(&mut macros, config);
+        }
+        persist_macros(&macros);
+        log::info!("Registered {} of {} macros. Total macros: {}", registered, results.len(), macros.len());
+    }
+    results
 }
 
 // Command to get all registered macros
 #[tauri::command]
 fn get_macros() -> Result<Vec<MacroConfig>, String> {
-    let macros = APP_STATE.registered_macros.lock().unwrap();
+    let macros = lock_or_recover(&APP_STATE.registered_macros);
     Ok(macros.clone()) // Return a clone of the stored macros
 }
 
+// Command to get the macros of one group, or the ungrouped macros when group_id is None
+#[tauri::command]
+fn get_macros_by_group(group_id: Option<String>) -> Vec<MacroConfig> {
+    lock_or_recover(&APP_STATE.registered_macros)
+        .iter()
+        .filter(|m| m.groupId == group_id)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ActiveMacroInfo {
+    group_key: String,
+    last_triggered_ms: u64, // Milliseconds since the Unix epoch
+    has_before_state: bool,
+    has_before_notifier: bool,
+}
+
+// Read-only snapshot of groups with pending after_actions, for debugging stuck sessions
+#[tauri::command]
+fn get_active_macros() -> Result<Vec<ActiveMacroInfo>, String> {
+    let now = std::time::SystemTime::now();
+    let active: Vec<(String, std::time::Duration)> = lock_or_recover(&APP_STATE.active_macros)
+        .iter()
+        .map(|(key, active_macro)| (key.clone(), active_macro.last_triggered.elapsed()))
+        .collect();
+
+    let before_action_states = lock_or_recover(&APP_STATE.before_action_states);
+    let before_notifiers = lock_or_recover(&APP_STATE.before_notifiers);
+    Ok(active
+        .into_iter()
+        .map(|(group_key, elapsed)| ActiveMacroInfo {
+            last_triggered_ms: now
+                .checked_sub(elapsed)
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as u64),
+            has_before_state: before_action_states.contains_key(&group_key),
+            has_before_notifier: before_notifiers.contains_key(&group_key),
+            group_key,
+        })
+        .collect())
+}
+
+// Command to export the registered macros as pretty-printed JSON
+#[tauri::command]
+fn export_macros(path: String) -> Result<usize, String> {
+    let macros = lock_or_recover(&APP_STATE.registered_macros).clone();
+    let json = serde_json::to_string_pretty(&macros)
+        .map_err(|e| format!("Failed to serialize macros: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    log::info!("Exported {} macros to {}", macros.len(), path);
+    Ok(macros.len())
+}
+
+// Command to import macros from a JSON file, either merging by id or replacing
+// the whole set. Nothing is registered unless every imported macro validates.
+#[tauri::command]
+fn import_macros(path: String, merge: bool) -> Result<Vec<MacroConfig>, Vec<String>> {
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| vec![format!("Failed to read {}: {}", path, e)])?;
+    let mut imported: Vec<MacroConfig> = serde_json::from_str(&json)
+        .map_err(|e| vec![format!("Failed to parse {}: {}", path, e)])?;
+    imported.iter_mut().for_each(migrate_macro_config);
+
+    let problems: Vec<String> = imported.iter().flat_map(validate_macro).collect();
+    if !problems.is_empty() {
+        log::warn!("Rejected import of {} with {} problems", path, problems.len());
+        return Err(problems);
+    }
+
+    // Stop every macro being replaced or removed, like cancel_macro does: close its group's
+    // session so in-flight flows stop, abort pending after_actions, drop its before, toggle
+    // and undo state and release what it holds. This is done before taking the macro lock
+    // for the import, since active_macros is otherwise locked first.
+    let mut affected: Vec<(MacroId, GroupId)> = imported.iter()
+        .map(|m| (m.id.clone(), m.groupId.clone().unwrap_or_else(|| m.id.clone())))
+        .collect();
+    affected.extend(lock_or_recover(&APP_STATE.registered_macros).iter()
+        .filter(|m| !merge || imported.iter().any(|config| config.id == m.id))
+        .map(|m| (m.id.clone(), m.groupId.clone().unwrap_or_else(|| m.id.clone()))));
+    for (id, group_key) in &affected {
+        begin_group_session(group_key);
+        if let Some(active_macro) = lock_or_recover(&APP_STATE.active_macros).remove(group_key) {
+            active_macro.abort_handle.abort();
+            log::info!("Aborted pending after_actions for group {}.", group_key);
+        }
+        forget_before_state(group_key);
+        lock_or_recover(&APP_STATE.undo_stacks).remove(group_key);
+        lock_or_recover(&APP_STATE.macro_toggle_states).remove(id);
+        lock_or_recover(&APP_STATE.held_momentary_macros).remove(id);
+        cleanup_mouse_state_for_macro(id);
+    }
+
+    let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+    if merge {
+        for config in &imported {
+            macros.retain(|m| m.id != config.id);
+        }
+        macros.extend(imported.iter().cloned());
+    } else {
+        *macros = imported.clone();
+    }
+    log::info!("Imported {} macros from {} (merge: {}). Total macros: {}", imported.len(), path, merge, macros.len());
+    persist_macros(&macros);
+    Ok(macros.clone())
+}
+
 // New command to cancel a macro (used when deactivating from frontend)
 #[tauri::command]
 fn cancel_macro(id: String) -> Result<(), String> {
-    println!("Attempting to cancel macro: {}", id);
+    log::info!("Attempting to cancel macro: {}", id);
     
     // First, remove from registered macros
     {
-        let mut macros = APP_STATE.registered_macros.lock().unwrap();
+        let mut macros = lock_or_recover(&APP_STATE.registered_macros);
         macros.retain(|m| m.id != id);
+        persist_macros(&macros);
     }
     
     // Then, abort any active after_actions task and clean up before_action_state
     {
-        let mut active_macros = APP_STATE.active_macros.lock().unwrap();
+        let mut active_macros = lock_or_recover(&APP_STATE.active_macros);
         if let Some(active_macro) = active_macros.remove(&id) {
             active_macro.abort_handle.abort();
-            println!("Aborted pending after_actions for macro {}.", id);
+            log::info!("Aborted pending after_actions for macro {}.", id);
         }
         
         // Also remove any before_action_state
-        let mut before_action_states = APP_STATE.before_action_states.lock().unwrap();
+        let mut before_action_states = lock_or_recover(&APP_STATE.before_action_states);
         if before_action_states.remove(&id).is_some() {
-            println!("Removed before_action_state for macro {}.", id);
+            log::info!("Removed before_action_state for macro {}.", id);
         }
     }
+    lock_or_recover(&APP_STATE.macro_toggle_states).remove(&id);
     cleanup_mouse_state_for_macro(&id);
 
-    println!("Macro {} successfully canceled", id);
+    log::info!("Macro {} successfully canceled", id);
     Ok(())
 }
 
-// Command to list MIDI inputs
+// Command to enable or disable a macro without unregistering it
 #[tauri::command]
-fn list_midi_inputs_rust() -> Result<Vec<String>, String> {
-    let midi_in = MidiInput::new("opengrader-midi-input").map_err(|e| {
+fn set_macro_enabled(id: String, enabled: bool) -> Result<(), String> {
+    let group_key = {
+        let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+        let macro_config = macros
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or_else(|| format!("Macro {} is not registered", id))?;
+        macro_config.enabled = Some(enabled);
+        let group_key = macro_config.groupId.clone().unwrap_or_else(|| macro_config.id.clone());
+        persist_macros(&macros);
+        group_key
+    };
+    log::info!("Macro {} {}", id, if enabled { "enabled" } else { "disabled" });
+
+    // Disabling behaves like cancel_macro for anything still pending
+    if !enabled {
+        if let Some(active_macro) = lock_or_recover(&APP_STATE.active_macros).remove(&group_key) {
+            active_macro.abort_handle.abort();
+            log::info!("Aborted pending after_actions for macro {}.", id);
+        }
+        lock_or_recover(&APP_STATE.before_action_states).remove(&group_key);
+        cleanup_mouse_state_for_macro(&id);
+    }
+    Ok(())
+}
+
+// Command to run a macro as if its MIDI trigger had arrived, for previewing from the UI.
+// It goes through the same before/main/after flow; there is no trigger message, so
+// velocity scaling and encoder/NRPN specifics don't apply. Disabled macros run too.
+#[tauri::command]
+fn trigger_macro<R: Runtime>(app_handle: AppHandle<R>, id: String) -> Result<(), String> {
+    let macro_config = lock_or_recover(&APP_STATE.registered_macros)
+        .iter()
+        .find(|m| m.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Macro {} is not registered", id))?;
+    log::info!("Manually triggering macro: {}", macro_config.name);
+
+    if !spawn_macro_trigger(macro_config, None, app_handle) {
+        return Err("Too many macros are running; try again shortly".to_string());
+    }
+    Ok(())
+}
+
+// Action list of a macro for a phase name as used in ActionContext
+fn phase_actions_mut<'a>(config: &'a mut MacroConfig, phase: &str) -> Result<Option<&'a mut Vec<MacroAction>>, String> {
+    match phase {
+        "before" => Ok(config.before_actions.as_mut()),
+        "main" => Ok(Some(&mut config.actions)),
+        "increment" => Ok(config.increment_actions.as_mut()),
+        "decrement" => Ok(config.decrement_actions.as_mut()),
+        "alternate" => Ok(config.alternate_actions.as_mut()),
+        "after" => Ok(config.after_actions.as_mut()),
+        _ => Err(format!("Invalid phase '{}'; expected before, main, increment, decrement, alternate or after", phase)),
+    }
+}
+
+// Command to move one action of a macro's phase to another position. `to` is the
+// action's index after the move. Returns the phase's actions in their new order.
+#[tauri::command]
+fn reorder_actions(macro_id: String, phase: String, from: usize, to: usize) -> Result<Vec<MacroAction>, String> {
+    let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+    let macro_config = macros
+        .iter_mut()
+        .find(|m| m.id == macro_id)
+        .ok_or_else(|| format!("Macro {} is not registered", macro_id))?;
+    let actions = match phase_actions_mut(macro_config, &phase)? {
+        Some(actions) if from < actions.len() && to < actions.len() => actions,
+        actions => {
+            let len = actions.map_or(0, |a| a.len());
+            return Err(format!("Cannot move {} action {} to {}: macro {} has {} {} actions", phase, from, to, macro_id, len, phase));
+        },
+    };
+
+    let action = actions.remove(from);
+    actions.insert(to, action);
+    let reordered = actions.clone();
+    persist_macros(&macros);
+    log::info!("Moved {} action {} to {} in macro {}", phase, from, to, macro_id);
+    Ok(reordered)
+}
+
+// Command to change a macro's display name in place, keeping its position and runtime state
+#[tauri::command]
+fn rename_macro(id: String, new_name: String) -> Result<(), String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Macro name cannot be empty".to_string());
+    }
+    let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+    let macro_config = macros
+        .iter_mut()
+        .find(|m| m.id == id)
+        .ok_or_else(|| format!("Macro {} is not registered", id))?;
+    log::info!("Renaming macro {} from '{}' to '{}'", id, macro_config.name, new_name);
+    macro_config.name = new_name.to_string();
+    persist_macros(&macros);
+    Ok(())
+}
+
+// Move a group's entry in a runtime state map to its new key
+fn rekey_group_state<V>(map: &Mutex<HashMap<String, V>>, old_group: &str, new_group: &str) {
+    let mut map = lock_or_recover(map);
+    if let Some(value) = map.remove(old_group) {
+        map.insert(new_group.to_string(), value);
+    }
+}
+
+// Command to rename a group: every macro with groupId == old_group moves to new_group.
+// Renaming closes the group's current session first: runs in flight stop at their next
+// session check, pending after_actions run now and the next trigger under the new name
+// starts fresh with its before_actions. Trigger timing and the undo stack follow the group.
+#[tauri::command]
+async fn rename_group<R: Runtime>(app_handle: AppHandle<R>, old_group: String, new_group: String) -> Result<usize, String> {
+    let new_group = new_group.trim().to_string();
+    if new_group.is_empty() {
+        return Err("Group name cannot be empty".to_string());
+    }
+    if new_group == old_group {
+        return Ok(0);
+    }
+
+    // Released before the runtime state below is touched, which takes active_macros
+    // (locked before registered_macros elsewhere)
+    let (renamed, group_config) = {
+        let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+        // Group keys share a namespace with ungrouped macro ids, so both must be free
+        if macros.iter().any(|m| m.groupId.as_deref() == Some(new_group.as_str()) || (m.groupId.is_none() && m.id == new_group)) {
+            return Err(format!("Group {} already exists", new_group));
+        }
+        let mut renamed = 0;
+        for macro_config in macros.iter_mut().filter(|m| m.groupId.as_deref() == Some(old_group.as_str())) {
+            macro_config.groupId = Some(new_group.clone());
+            renamed += 1;
+        }
+        let Some(group_config) = macros.iter().find(|m| m.groupId.as_deref() == Some(new_group.as_str())).cloned() else {
+            return Err(format!("No macros belong to group {}", old_group));
+        };
+        persist_macros(&macros);
+        (renamed, group_config)
+    };
+    log::info!("Renamed group {} to {} ({} macros)", old_group, new_group, renamed);
+
+    // Close the old session. Flows still running under the old key can no longer match it,
+    // so their pending after_actions run here instead of being skipped.
+    begin_group_session(&old_group);
+    let pending = lock_or_recover(&APP_STATE.active_macros).remove(&old_group);
+    if let Some(active_macro) = pending {
+        active_macro.abort_handle.abort();
+        if let Some(after_actions) = group_config.after_actions.as_deref().filter(|a| !a.is_empty()) {
+            log::debug!("Running pending after_actions of renamed group {}", old_group);
+            let context = ActionContext::new(&group_config, "after", None);
+            run_actions(after_actions, &context, &app_handle).await;
+        }
+    }
+    lock_or_recover(&APP_STATE.before_action_states).remove(&old_group);
+    if let Some(notifier) = take_before_notifier(&old_group) {
+        notifier.notify_waiters();
+    }
+
+    rekey_group_state(&APP_STATE.last_group_triggers, &old_group, &new_group);
+    rekey_group_state(&APP_STATE.last_scroll_triggers, &old_group, &new_group);
+    rekey_group_state(&APP_STATE.undo_stacks, &old_group, &new_group);
+    Ok(renamed)
+}
+
+const GROUP_RESET_EVENT: &str = "group-reset";
+
+// Command to recover a single group stuck in a confused state (e.g. before_actions marked
+// as started but never cleared) without the global emergency release. In-flight runs stop
+// at their next session check, inputs they hold are released, pending after_actions are
+// dropped and the next trigger starts fresh with its before_actions. `group_id` is a group
+// id or an ungrouped macro's id.
+#[tauri::command]
+fn reset_group<R: Runtime>(app_handle: AppHandle<R>, group_id: String) -> Result<(), String> {
+    begin_group_session(&group_id);
+    if let Some(active_macro) = lock_or_recover(&APP_STATE.active_macros).remove(&group_id) {
+        active_macro.abort_handle.abort();
+    }
+    lock_or_recover(&APP_STATE.before_action_states).remove(&group_id);
+    if let Some(notifier) = take_before_notifier(&group_id) {
+        notifier.notify_waiters();
+    }
+    lock_or_recover(&APP_STATE.last_group_triggers).remove(&group_id);
+
+    // Release whatever the stopped runs were holding down
+    let macro_ids: Vec<MacroId> = lock_or_recover(&APP_STATE.registered_macros)
+        .iter()
+        .filter(|m| m.groupId.as_ref().unwrap_or(&m.id) == &group_id)
+        .map(|m| m.id.clone())
+        .collect();
+    for macro_id in &macro_ids {
+        cleanup_mouse_state_for_macro(macro_id);
+    }
+    log::info!("Reset group {}", group_id);
+
+    if let Err(e) = app_handle.emit(GROUP_RESET_EVENT, &group_id) {
+        log::error!("Failed to emit {}: {}", GROUP_RESET_EVENT, e);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MidiPortInfo {
+    index: usize, // What start_midi_listening_rust takes; can change as devices come and go
+    name: String,
+    // OS identifier from midir (e.g. the CoreMIDI unique id); tells apart ports with the same name
+    id: String,
+    // 1 for the first port with this name, 2 for the second, ...
+    occurrence: usize,
+}
+
+// Enumerate the input ports and store them in app state for later reference
+fn refresh_midi_ports() -> Result<Vec<MidiPortInfo>, String> {
+    let midi_in = MidiInput::new(&midi_client_name()).map_err(|e| {
         #[cfg(target_os = "macos")]
         return format!("Failed to initialize MIDI on macOS: {}. Please ensure your app has the necessary permissions in System Preferences > Security & Privacy > Privacy > Microphone and Bluetooth.", e);
         
@@ -540,39 +2552,133 @@ fn list_midi_inputs_rust() -> Result<Vec<String>, String> {
         return e.to_string();
     })?;
     
-    let ports = midi_in.ports();
-    let mut port_names = Vec::new();
-    
-    // Store ports in app state for later reference
-    let mut app_ports = APP_STATE.midi_ports.lock().unwrap();
-    app_ports.clear();
-    
-    for (i, port) in ports.iter().enumerate() {
-        let port_name = midi_in.port_name(port).unwrap_or_else(|_| format!("Unknown port {}", i));
-        port_names.push(port_name.clone());
-        app_ports.push((port_name, i));
+    let mut ports: Vec<MidiPortInfo> = Vec::new();
+    for (i, port) in midi_in.ports().iter().enumerate() {
+        let name = midi_in.port_name(port).unwrap_or_else(|_| format!("Unknown port {}", i));
+        let occurrence = ports.iter().filter(|p| p.name == name).count() + 1;
+        ports.push(MidiPortInfo { index: i, name, id: port.id(), occurrence });
     }
     
-    Ok(port_names)
+    *lock_or_recover(&APP_STATE.midi_ports) = ports.clone();
+    Ok(ports)
+}
+
+// Command to list MIDI inputs
+#[tauri::command]
+fn list_midi_inputs_rust() -> Result<Vec<String>, String> {
+    Ok(refresh_midi_ports()?.into_iter().map(|port| port.name).collect())
+}
+
+// Command to list MIDI inputs with their OS identifiers, for telling same-named devices apart.
+// midir doesn't expose manufacturer information, so none is included.
+#[tauri::command]
+fn list_midi_inputs_detailed() -> Result<Vec<MidiPortInfo>, String> {
+    refresh_midi_ports()
+}
+
+// Command to list MIDI outputs, indexed the same way connect_midi_output expects
+#[tauri::command]
+fn list_midi_outputs() -> Result<Vec<String>, String> {
+    let midi_out = MidiOutput::new("opengrader-midi-output")
+        .map_err(|e| create_midi_error("Failed to create MIDI output", e))?;
+    Ok(midi_out
+        .ports()
+        .iter()
+        .enumerate()
+        .map(|(i, port)| midi_out.port_name(port).unwrap_or_else(|_| format!("Unknown port {}", i)))
+        .collect())
+}
+
+// Command to open the output port SendMidi actions write to, replacing any previous one
+#[tauri::command]
+fn connect_midi_output(port_index: usize) -> Result<(), String> {
+    let midi_out = MidiOutput::new("opengrader-midi-output")
+        .map_err(|e| create_midi_error("Failed to create MIDI output", e))?;
+    let ports = midi_out.ports();
+    let port = ports.get(port_index).ok_or_else(|| {
+        format!("Output port index {} out of range. Only {} ports available.", port_index, ports.len())
+    })?;
+    let port_name = midi_out.port_name(port).unwrap_or_else(|_| format!("Unknown port {}", port_index));
+    let connection = midi_out
+        .connect(port, "opengrader-output")
+        .map_err(|e| create_midi_error("Failed to connect to MIDI output", e))?;
+
+    if let Some(previous) = lock_or_recover(&APP_STATE.midi_output).replace(connection) {
+        previous.close();
+    }
+    log::info!("Connected MIDI output: {}", port_name);
+    Ok(())
+}
+
+// Make `layer` the active layer and tell the frontend through a layer-changed event
+fn switch_layer<R: Runtime>(layer: String, app_handle: Option<&AppHandle<R>>) {
+    {
+        let mut current_layer = lock_or_recover(&APP_STATE.current_layer);
+        if *current_layer == layer {
+            return;
+        }
+        log::info!("Switching layer: {} -> {}", current_layer, layer);
+        *current_layer = layer.clone();
+    }
+    if let Some(app) = app_handle {
+        let _ = app.emit(LAYER_CHANGED_EVENT, layer);
+    }
+}
+
+// Command to switch the active macro layer
+#[tauri::command]
+fn set_active_layer<R: Runtime>(app_handle: AppHandle<R>, name: String) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Layer name must not be empty".to_string());
+    }
+    switch_layer(name, Some(&app_handle));
+    Ok(())
+}
+
+#[tauri::command]
+fn get_active_layer() -> Result<String, String> {
+    Ok(lock_or_recover(&APP_STATE.current_layer).clone())
+}
+
+fn send_midi_message(params: &ActionParams) -> Result<(), String> {
+    let bytes = params.midi_bytes.as_deref().ok_or("Missing midi_bytes parameter for SendMidi")?;
+    let mut output = lock_or_recover(&APP_STATE.midi_output);
+    let connection = output.as_mut().ok_or("No MIDI output connected")?;
+    log::debug!("Executing SendMidi: {}", format_hex_bytes(bytes));
+    connection.send(bytes).map_err(|e| format!("Failed to send MIDI message: {}", e))
 }
 
 // Start listening to a MIDI input by index
 // Add these type aliases at the top of your file (after imports)
 type MacroId = String;
 type GroupId = String;
-type TimestampMs = u64;
+type TimestampUs = u64; // Microseconds on the midi_timestamp clock
 
 // Add these new structures for better organization
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct MidiData {
     status: u8,
     message_type: MidiMessageType,
-    channel: u8,
+    channel: u8, // 1-16, or 0 for system messages
     data1: u8,
     data2: u8,
+    // (MSB controller, 0-16383 value) once a 14-bit CC pair has been assembled
+    cc_14bit: Option<(u8, u16)>,
+    // Signed pitch-bend value (-8192..8191) for PitchBend messages
+    pitch_bend: Option<i16>,
+    // Pitch-bend value previously seen on this channel (0 / center if none yet)
+    previous_pitch_bend: Option<i16>,
+    // Pressure previously seen on this channel (and note, for aftertouch); 0 if none yet
+    previous_pressure: Option<u8>,
+    // Full SysEx message including the F0/F7 framing bytes
+    sysex: Option<Vec<u8>>,
+    // (14-bit parameter, 14-bit value) once an NRPN data entry has been assembled
+    nrpn: Option<(u16, u16)>,
+    // NoteOn velocity as received, when the velocity curve changed data2
+    raw_velocity: Option<u8>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 enum MidiMessageType {
     NoteOff,
     NoteOn,
@@ -581,17 +2687,10 @@ enum MidiMessageType {
     ProgramChange,
     ChannelPressure,
     PitchBend,
+    SysEx,
     Other,
 }
 
-// Macro for MIDI logging (can be easily disabled by commenting out the println! line)
-macro_rules! midi_log {
-    ($($arg:tt)*) => { 
-        // Comment out the next line to disable MIDI logging
-        println!($($arg)*); 
-    }
-}
-
 // Helper function to create platform-specific MIDI errors
 fn create_midi_error(base_error: &str, err: impl std::fmt::Display) -> String {
     #[cfg(target_os = "macos")]
@@ -609,91 +2708,393 @@ fn create_midi_error(base_error: &str, err: impl std::fmt::Display) -> String {
 }
 
 // Helper functions for the refactored MIDI listening
-fn cleanup_existing_connection() -> Result<(), String> {
-    let mut connection_guard = APP_STATE.midi_connection.lock().unwrap();
-    if connection_guard.is_some() {
-        *connection_guard = None;
-    }
+fn cleanup_existing_connection(port_index: usize) -> Result<(), String> {
+    retire_watchdog(port_index);
+    lock_or_recover(&APP_STATE.midi_connections).remove(&port_index);
     Ok(())
 }
 
 fn validate_and_get_port_name(port_index: usize) -> Result<String, String> {
-    let ports_guard = APP_STATE.midi_ports.lock().unwrap();
+    let ports_guard = lock_or_recover(&APP_STATE.midi_ports);
     if port_index >= ports_guard.len() {
         return Err(format!("Port index {} out of range. Only {} ports available.", 
                           port_index, ports_guard.len()));
     }
-    Ok(ports_guard[port_index].0.clone())
+    Ok(ports_guard[port_index].name.clone())
 }
 
 fn create_midi_input() -> Result<MidiInput, String> {
-    MidiInput::new("opengrader-midi-listener")
+    MidiInput::new(&midi_client_name())
         .map_err(|e| create_midi_error("Failed to create MIDI listener", e))
 }
 
-fn parse_midi_message(message: &[u8]) -> Option<MidiData> {
-    if message.len() < 3 {
-        return None;
+// Total length (status + data bytes) a message with this status byte should have
+fn expected_midi_length(status: u8) -> usize {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        // System Common messages with data; SysEx is variable-length and kept whole in MidiData.sysex
+        0xF2 => 3,
+        0xF1 | 0xF3 => 2,
+        _ => 1,
     }
+}
+
+fn parse_midi_message(message: &[u8]) -> Option<MidiData> {
+    let status = *message.first()?;
     
-    let status = message[0];
+    // Data bytes the status doesn't define are ignored; truncated ones are read as 0
+    let expected_len = expected_midi_length(status);
+    let data_byte = |index: usize| if index < expected_len { message.get(index).copied().unwrap_or(0) } else { 0 };
+    let data1 = data_byte(1);
+    let data2 = data_byte(2);
     let message_type_u8 = status & 0xF0;
-    let channel = (status & 0x0F) + 1;
+    // System messages (0xF0 and up) use the low nibble for their type, not a channel
+    let channel = if status >= 0xF0 { 0 } else { (status & 0x0F) + 1 };
     
     let message_type = match message_type_u8 {
         0x80 => MidiMessageType::NoteOff,
+        // Many controllers send NoteOn with velocity 0 instead of NoteOff
+        0x90 if data2 == 0 => MidiMessageType::NoteOff,
         0x90 => MidiMessageType::NoteOn,
         0xA0 => MidiMessageType::Aftertouch,
         0xB0 => MidiMessageType::ControlChange,
         0xC0 => MidiMessageType::ProgramChange,
         0xD0 => MidiMessageType::ChannelPressure,
         0xE0 => MidiMessageType::PitchBend,
+        _ if status == 0xF0 => MidiMessageType::SysEx,
         _ => MidiMessageType::Other,
     };
     
+    // Pitch bend carries a 14-bit value: data1 is the LSB, data2 the MSB, 8192 is center
+    let pitch_bend = if message_type == MidiMessageType::PitchBend {
+        Some((((data2 as i16) << 7) | data1 as i16) - 8192)
+    } else {
+        None
+    };
+    
+    let sysex = (message_type == MidiMessageType::SysEx).then(|| message.to_vec());
+    
     Some(MidiData {
         status,
         message_type,
-        channel: channel as u8,
-        data1: message[1],
-        data2: message[2],
+        channel,
+        data1,
+        data2,
+        cc_14bit: None,
+        pitch_bend,
+        previous_pitch_bend: None,
+        previous_pressure: None,
+        sysex,
+        nrpn: None,
+        raw_velocity: None,
     })
 }
 
-fn should_trigger_macro(macro_config: &MacroConfig, midi_data: &MidiData) -> bool {
-    if macro_config.midi_channel != midi_data.channel {
-        return false;
+// Map a NoteOn velocity through the curve 127 * (v / 127)^gamma. Hits stay hits (1 at
+// least) so the curve never turns a NoteOn into a NoteOff; invalid gammas leave it as is.
+fn remap_velocity(velocity: u8, gamma: f64) -> u8 {
+    if velocity == 0 || !gamma.is_finite() || gamma <= 0.0 || gamma == 1.0 {
+        return velocity;
     }
-    
-    match midi_data.message_type {
+    let normalized = velocity.min(127) as f64 / 127.0;
+    (127.0 * normalized.powf(gamma)).round().clamp(1.0, 127.0) as u8
+}
+
+// Apply the configured velocity curve to NoteOn messages before matching and emission,
+// keeping the received value in raw_velocity
+fn apply_velocity_curve(midi_data: &mut MidiData) {
+    if midi_data.message_type != MidiMessageType::NoteOn {
+        return;
+    }
+    let gamma = lock_or_recover(&APP_STATE.global_settings).velocity_gamma;
+    let corrected = remap_velocity(midi_data.data2, gamma);
+    if corrected != midi_data.data2 {
+        midi_data.raw_velocity = Some(midi_data.data2);
+        midi_data.data2 = corrected;
+    }
+}
+
+// Record this channel's pitch-bend value and attach the previous one for crossing detection
+fn track_pitch_bend(midi_data: &mut MidiData) {
+    if let Some(bend) = midi_data.pitch_bend {
+        let mut last_pitch_bend = lock_or_recover(&APP_STATE.last_pitch_bend);
+        midi_data.previous_pitch_bend = Some(last_pitch_bend.insert(midi_data.channel, bend).unwrap_or(0));
+    }
+}
+
+// Record channel pressure / poly aftertouch and attach the previous value for crossing detection
+fn track_pressure(midi_data: &mut MidiData) {
+    let (key, pressure) = match midi_data.message_type {
+        MidiMessageType::ChannelPressure => ((midi_data.channel, None), midi_data.data1),
+        MidiMessageType::Aftertouch => ((midi_data.channel, Some(midi_data.data1)), midi_data.data2),
+        _ => return,
+    };
+    let mut last_pressure = lock_or_recover(&APP_STATE.last_pressure);
+    midi_data.previous_pressure = Some(last_pressure.insert(key, pressure).unwrap_or(0));
+}
+
+// Fire once when pressure rises to the threshold, not on every message while it's held
+fn pressure_crossed(threshold: u8, current: u8, midi_data: &MidiData) -> bool {
+    current >= threshold && midi_data.previous_pressure.unwrap_or(0) < threshold
+}
+
+fn pitch_bend_crossed(macro_config: &MacroConfig, midi_data: &MidiData) -> bool {
+    let (threshold, current) = match (macro_config.pitch_threshold, midi_data.pitch_bend) {
+        (Some(threshold), Some(current)) => (threshold, current),
+        _ => return false,
+    };
+    let previous = midi_data.previous_pitch_bend.unwrap_or(0);
+    let direction = macro_config.pitch_direction.unwrap_or(if threshold >= 0 {
+        PitchDirection::Up
+    } else {
+        PitchDirection::Down
+    });
+    
+    match direction {
+        PitchDirection::Up => previous < threshold && current >= threshold,
+        PitchDirection::Down => previous > threshold && current <= threshold,
+    }
+}
+
+// How long an MSB waits for its matching LSB before the pair is discarded
+const CC_14BIT_PAIR_WINDOW: std::time::Duration = std::time::Duration::from_millis(50);
+
+// Buffer 14-bit CC MSBs (controllers 0-31) and, when the matching LSB (controller + 32)
+// arrives within the window, attach the assembled value to the MidiData
+fn assemble_14bit_cc(midi_data: &mut MidiData) {
+    if midi_data.message_type != MidiMessageType::ControlChange {
+        return;
+    }
+
+    let mut pending = lock_or_recover(&APP_STATE.pending_cc_msb);
+    match midi_data.data1 {
+        0..=31 => {
+            pending.insert(
+                (midi_data.channel, midi_data.data1),
+                (midi_data.data2, std::time::Instant::now()),
+            );
+        },
+        32..=63 => {
+            let msb_controller = midi_data.data1 - 32;
+            if let Some((msb, received_at)) = pending.remove(&(midi_data.channel, msb_controller)) {
+                if received_at.elapsed() <= CC_14BIT_PAIR_WINDOW {
+                    let value = ((msb as u16) << 7) | midi_data.data2 as u16;
+                    midi_data.cc_14bit = Some((msb_controller, value));
+                }
+            }
+        },
+        _ => {},
+    }
+}
+
+// NRPN controllers: parameter number MSB/LSB, data entry MSB/LSB, and RPN select
+// (which means following data entries are no longer for an NRPN)
+const CC_NRPN_PARAM_MSB: u8 = 99;
+const CC_NRPN_PARAM_LSB: u8 = 98;
+const CC_DATA_ENTRY_MSB: u8 = 6;
+const CC_DATA_ENTRY_LSB: u8 = 38;
+const CC_RPN_PARAM_MSB: u8 = 101;
+const CC_RPN_PARAM_LSB: u8 = 100;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct NrpnState {
+    param_msb: Option<u8>,
+    param_lsb: Option<u8>,
+    value_msb: Option<u8>,
+}
+
+// Follow the CC 99/98 (parameter) then CC 6/38 (value) sequence on each channel and attach
+// the assembled NRPN to the data entry that completes it. The data entry MSB completes it
+// with a 7-bit value (msb << 7), as many devices never send an LSB; an LSB that follows
+// refines that value and is reported again. The parameter stays selected, so further
+// data entries update the same parameter.
+fn assemble_nrpn(midi_data: &mut MidiData) {
+    if midi_data.message_type != MidiMessageType::ControlChange {
+        return;
+    }
+
+    let mut states = lock_or_recover(&APP_STATE.nrpn_states);
+    let state = states.entry(midi_data.channel).or_default();
+    match midi_data.data1 {
+        CC_NRPN_PARAM_MSB => *state = NrpnState { param_msb: Some(midi_data.data2), ..NrpnState::default() },
+        CC_NRPN_PARAM_LSB => {
+            state.param_lsb = Some(midi_data.data2);
+            state.value_msb = None;
+        },
+        CC_RPN_PARAM_MSB | CC_RPN_PARAM_LSB => *state = NrpnState::default(),
+        CC_DATA_ENTRY_MSB | CC_DATA_ENTRY_LSB => {
+            let (Some(param_msb), Some(param_lsb)) = (state.param_msb, state.param_lsb) else {
+                return;
+            };
+            let (value_msb, value_lsb) = if midi_data.data1 == CC_DATA_ENTRY_MSB {
+                state.value_msb = Some(midi_data.data2);
+                (midi_data.data2, 0)
+            } else {
+                match state.value_msb {
+                    Some(value_msb) => (value_msb, midi_data.data2),
+                    None => return,
+                }
+            };
+            let param = ((param_msb as u16) << 7) | param_lsb as u16;
+            let value = ((value_msb as u16) << 7) | value_lsb as u16;
+            midi_data.nrpn = Some((param, value));
+        },
+        _ => {},
+    }
+}
+
+// Match the inclusive midi_note_min..=midi_note_max range when both bounds are set,
+// otherwise the exact midi_note
+fn note_matches(macro_config: &MacroConfig, note: u8) -> bool {
+    match (macro_config.midi_note_min, macro_config.midi_note_max) {
+        (Some(min), Some(max)) => (min..=max).contains(&note),
+        _ => macro_config.midi_note == note,
+    }
+}
+
+// NoteOff for a momentary macro's note, which releases what the macro holds
+fn is_momentary_release(macro_config: &MacroConfig, midi_data: &MidiData) -> bool {
+    macro_config.momentary == Some(true)
+        && midi_data.message_type == MidiMessageType::NoteOff
+        && (macro_config.midi_channel == MIDI_CHANNEL_ANY || macro_config.midi_channel == midi_data.channel)
+        && note_matches(macro_config, midi_data.data1)
+}
+
+fn release_momentary_macro(macro_config: &MacroConfig) {
+    if lock_or_recover(&APP_STATE.held_momentary_macros).remove(&macro_config.id) {
+        log::info!("Note released for momentary macro: {}", macro_config.name);
+        cleanup_mouse_state_for_macro(&macro_config.id);
+    }
+}
+
+// Signed delta of a relative encoder CC value, or None in absolute mode
+fn decode_relative_cc(mode: EncoderMode, value: u8) -> Option<i8> {
+    let value = value & 0x7F;
+    match mode {
+        EncoderMode::Absolute => None,
+        EncoderMode::RelativeTwosComplement => {
+            Some(if value >= 64 { (value as i16 - 128) as i8 } else { value as i8 })
+        },
+        EncoderMode::RelativeSignedBit => {
+            let magnitude = (value & 0x3F) as i8;
+            Some(if value & 0x40 != 0 { -magnitude } else { magnitude })
+        },
+    }
+}
+
+fn encoder_delta(macro_config: &MacroConfig, midi_data: &MidiData) -> Option<i8> {
+    if midi_data.message_type != MidiMessageType::ControlChange {
+        return None;
+    }
+    decode_relative_cc(macro_config.encoder_mode.unwrap_or_default(), midi_data.data2)
+}
+
+fn sysex_matches(macro_config: &MacroConfig, payload: &[u8]) -> bool {
+    match &macro_config.sysex_pattern {
+        Some(pattern) if macro_config.sysex_exact == Some(true) => payload == pattern.as_slice(),
+        Some(pattern) => payload.starts_with(pattern),
+        None => false,
+    }
+}
+
+fn should_trigger_macro(macro_config: &MacroConfig, midi_data: &MidiData) -> bool {
+    if let Some(layer) = &macro_config.layer {
+        if *layer != *lock_or_recover(&APP_STATE.current_layer) {
+            return false;
+        }
+    }
+
+    // SysEx has no channel; SysEx macros match only SysEx messages and vice versa
+    if macro_config.sysex_pattern.is_some() || midi_data.message_type == MidiMessageType::SysEx {
+        return midi_data.sysex.as_deref().is_some_and(|payload| sysex_matches(macro_config, payload));
+    }
+
+    // Channels are 1-16 as produced by parse_midi_message; 0 matches any channel
+    if macro_config.midi_channel != MIDI_CHANNEL_ANY && macro_config.midi_channel != midi_data.channel {
+        return false;
+    }
+    
+    match midi_data.message_type {
         MidiMessageType::ControlChange => {
+            // NRPN macros only fire on a fully assembled parameter/value sequence
+            if let Some(nrpn_param) = macro_config.nrpn_param {
+                return midi_data.nrpn.is_some_and(|(param, _)| param == nrpn_param);
+            }
+            // Relative encoders fire on every movement of the controller, whatever the value
+            if let Some(delta) = encoder_delta(macro_config, midi_data) {
+                return macro_config.midi_note == midi_data.data1 && delta != 0;
+            }
+            // 14-bit macros only fire on a fully assembled MSB/LSB pair
+            if let Some(value_14bit) = macro_config.midi_value_14bit {
+                return midi_data.cc_14bit.is_some_and(|(controller, value)| {
+                    macro_config.midi_note == controller && value_14bit == value
+                });
+            }
+            if macro_config.midi_value_min.is_some() || macro_config.midi_value_max.is_some() {
+                let min = macro_config.midi_value_min.unwrap_or(0);
+                let max = macro_config.midi_value_max.unwrap_or(127);
+                return macro_config.midi_note == midi_data.data1 && (min..=max).contains(&midi_data.data2);
+            }
+            let tolerance = macro_config.midi_value_tolerance.unwrap_or(0);
             macro_config.midi_note == midi_data.data1 && 
-            macro_config.midi_value.map_or(false, |v| v == midi_data.data2)
+            macro_config.midi_value.map_or(false, |v| v.abs_diff(midi_data.data2) <= tolerance)
         },
         MidiMessageType::NoteOn => {
             // For Note On messages, match the note number and optionally the velocity
-            macro_config.midi_note == midi_data.data1 && 
+            macro_config.trigger_on.unwrap_or_default() != TriggerEdge::NoteOff &&
+            note_matches(macro_config, midi_data.data1) && 
             macro_config.midi_value.map_or(true, |v| v == midi_data.data2)
         },
         MidiMessageType::NoteOff => {
             // For Note Off messages, match the note number and optionally the velocity
-            macro_config.midi_note == midi_data.data1 && 
+            macro_config.momentary != Some(true) &&
+            macro_config.trigger_on.unwrap_or_default() != TriggerEdge::NoteOn &&
+            note_matches(macro_config, midi_data.data1) && 
             macro_config.midi_value.map_or(true, |v| v == midi_data.data2)
         },
+        MidiMessageType::PitchBend => pitch_bend_crossed(macro_config, midi_data),
+        // Pressure macros opt in with pressure_threshold and fire when the pressure rises past it
+        MidiMessageType::ChannelPressure => {
+            macro_config.pressure_threshold.is_some_and(|threshold| pressure_crossed(threshold, midi_data.data1, midi_data))
+        },
+        MidiMessageType::Aftertouch => {
+            note_matches(macro_config, midi_data.data1) &&
+            macro_config.pressure_threshold.is_some_and(|threshold| pressure_crossed(threshold, midi_data.data2, midi_data))
+        },
+        // midi_note holds the program number; Program Change has no second data byte
+        MidiMessageType::ProgramChange => macro_config.midi_note == midi_data.data1,
         // Add other message types as needed
         _ => false,
     }
 }
 
+// True if this macro already fired within its debounce window; otherwise records this trigger.
+// Unlike macro_trigger_delay, which spaces different groups, this drops duplicates of one macro.
+fn is_debounced(macro_config: &MacroConfig) -> bool {
+    let Some(debounce_ms) = macro_config.debounce_ms.filter(|ms| *ms > 0) else {
+        return false;
+    };
+    let now = std::time::Instant::now();
+    let mut last_triggers = lock_or_recover(&APP_STATE.last_macro_triggers);
+    if last_triggers.get(&macro_config.id).is_some_and(|last| {
+        now.duration_since(*last) < std::time::Duration::from_millis(debounce_ms as u64)
+    }) {
+        return true;
+    }
+    last_triggers.insert(macro_config.id.clone(), now);
+    false
+}
+
 fn calculate_trigger_delay(group_key: &str) -> Option<std::time::Duration> {
-    let settings = APP_STATE.global_settings.lock().unwrap();
+    let settings = lock_or_recover(&APP_STATE.global_settings);
     let delay_ms = settings.macro_trigger_delay;
     
     if delay_ms == 0 {
         return None;
     }
     
-    let mut last_group_triggers = APP_STATE.last_group_triggers.lock().unwrap();
+    let mut last_group_triggers = lock_or_recover(&APP_STATE.last_group_triggers);
     let now = std::time::Instant::now();
     
     // Find the most recent trigger from a different group
@@ -723,14 +3124,14 @@ fn calculate_trigger_delay(group_key: &str) -> Option<std::time::Duration> {
 
 // --- Session management helpers --------------------------------------------------
 fn begin_group_session(group_key: &str) -> u64 {
-    let mut sessions = APP_STATE.group_sessions.lock().unwrap();
+    let mut sessions = lock_or_recover(&APP_STATE.group_sessions);
     let entry = sessions.entry(group_key.to_string()).or_insert(0);
     *entry = entry.saturating_add(1);
     *entry
 }
 
 fn current_group_session(group_key: &str) -> u64 {
-    let sessions = APP_STATE.group_sessions.lock().unwrap();
+    let sessions = lock_or_recover(&APP_STATE.group_sessions);
     *sessions.get(group_key).unwrap_or(&0)
 }
 
@@ -739,48 +3140,115 @@ fn is_current_session(group_key: &str, session_id: u64) -> bool {
 }
 
 // Try to mark before-actions as started atomically. Returns true if we set it now.
-fn try_mark_before_started(state_key: &str) -> bool {
-    let mut before_action_states = APP_STATE.before_action_states.lock().unwrap();
-    if before_action_states.contains_key(state_key) {
-        return false;
+// Later triggers of the session are counted; with every_n, the one landing on the interval
+// re-runs them, unless a before run is still in progress (it then waits on that run's
+// notifier like any other trigger, since starting another run would replace the notifier
+// the in-progress one completes).
+fn try_mark_before_started(state_key: &str, every_n: Option<u32>, before_in_progress: bool) -> bool {
+    let mut before_action_states = lock_or_recover(&APP_STATE.before_action_states);
+    let Some(state) = before_action_states.get_mut(state_key) else {
+        before_action_states.insert(
+            state_key.to_string(),
+            BeforeActionState {
+                last_executed: std::time::Instant::now(),
+                cooldown: std::time::Duration::from_secs(0),
+                triggers: 1,
+            },
+        );
+        return true;
+    };
+
+    state.triggers = state.triggers.saturating_add(1);
+    let due = every_n.is_some_and(|n| n > 0 && (state.triggers - 1) % n == 0);
+    if due && !before_in_progress {
+        state.last_executed = std::time::Instant::now();
+        return true;
     }
-    before_action_states.insert(
-        state_key.to_string(),
-        BeforeActionState {
-            last_executed: std::time::Instant::now(),
-            cooldown: std::time::Duration::from_secs(0),
-        },
-    );
-    true
+    false
 }
 
 fn set_before_notifier(group_key: &str, notify: std::sync::Arc<tokio::sync::Notify>) {
-    APP_STATE
-        .before_notifiers
-        .lock()
-        .unwrap()
-        .insert(group_key.to_string(), notify);
+    lock_or_recover(&APP_STATE.before_notifiers).insert(group_key.to_string(), notify);
 }
 
 fn take_before_notifier(group_key: &str) -> Option<std::sync::Arc<tokio::sync::Notify>> {
-    APP_STATE
-        .before_notifiers
-        .lock()
-        .unwrap()
-        .remove(group_key)
+    lock_or_recover(&APP_STATE.before_notifiers).remove(group_key)
 }
 
 fn get_before_notifier(group_key: &str) -> Option<std::sync::Arc<tokio::sync::Notify>> {
-    APP_STATE
-        .before_notifiers
-        .lock()
-        .unwrap()
-        .get(group_key)
-        .cloned()
+    lock_or_recover(&APP_STATE.before_notifiers).get(group_key).cloned()
+}
+
+const MIDI_FLOOD_EVENT: &str = "midi-flood-detected";
+const FLOOD_EVENT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+struct MidiFloodEvent {
+    macro_id: String,
+    in_flight: usize,
+    limit: usize,
+}
+
+// One running handle_macro_trigger task, counted against max_concurrent_macros
+// until it is dropped at the end of the task
+struct TriggerSlot;
+
+impl TriggerSlot {
+    fn try_acquire() -> Option<Self> {
+        let limit = lock_or_recover(&APP_STATE.global_settings).max_concurrent_macros;
+        let mut in_flight = lock_or_recover(&APP_STATE.in_flight_triggers);
+        if limit != 0 && *in_flight >= limit {
+            return None;
+        }
+        *in_flight += 1;
+        Some(TriggerSlot)
+    }
+}
+
+impl Drop for TriggerSlot {
+    fn drop(&mut self) {
+        let mut in_flight = lock_or_recover(&APP_STATE.in_flight_triggers);
+        *in_flight = in_flight.saturating_sub(1);
+    }
+}
+
+// Run the trigger flow for a macro in the background. When max_concurrent_macros triggers
+// are already running (e.g. a faulty encoder spewing CCs) the trigger is dropped and a
+// throttled midi-flood-detected event is emitted instead. Returns whether it was started.
+fn spawn_macro_trigger<R: Runtime>(macro_config: MacroConfig, trigger: Option<MidiData>, app_handle: AppHandle<R>) -> bool {
+    let Some(slot) = TriggerSlot::try_acquire() else {
+        report_trigger_flood(&macro_config.id, &app_handle);
+        return false;
+    };
+    tauri::async_runtime::spawn(async move {
+        let _slot = slot;
+        handle_macro_trigger(macro_config, trigger, app_handle).await;
+    });
+    true
+}
+
+fn report_trigger_flood<R: Runtime>(macro_id: &str, app_handle: &AppHandle<R>) {
+    {
+        let mut last_flood_event = lock_or_recover(&APP_STATE.last_flood_event);
+        if last_flood_event.is_some_and(|at| at.elapsed() < FLOOD_EVENT_INTERVAL) {
+            return;
+        }
+        *last_flood_event = Some(std::time::Instant::now());
+    }
+    let event = MidiFloodEvent {
+        macro_id: macro_id.to_string(),
+        in_flight: *lock_or_recover(&APP_STATE.in_flight_triggers),
+        limit: lock_or_recover(&APP_STATE.global_settings).max_concurrent_macros,
+    };
+    log::warn!("Dropping triggers: {} macros already running (limit {}), latest {}", event.in_flight, event.limit, event.macro_id);
+    if let Err(e) = app_handle.emit(MIDI_FLOOD_EVENT, event) {
+        log::error!("Failed to emit {}: {}", MIDI_FLOOD_EVENT, e);
+    }
 }
 
 async fn handle_macro_trigger<R: Runtime>(
     macro_config: MacroConfig,
+    trigger: Option<MidiData>,
     app_handle: AppHandle<R>,
 ) {
     let group_key = macro_config.groupId.as_ref()
@@ -788,6 +3256,7 @@ async fn handle_macro_trigger<R: Runtime>(
         .clone();
     // Start a new session for this group to invalidate any concurrent older flows
     let session_id = begin_group_session(&group_key);
+    let started_at = std::time::Instant::now();
 
     // Inform frontend that this macro was triggered (for MIDI monitor UI)
     #[derive(Debug, Clone, Serialize)]
@@ -822,25 +3291,98 @@ async fn handle_macro_trigger<R: Runtime>(
     //    so the previous group's after_actions happen immediately and the new group's
     //    before/main are delayed as requested.
     if let Some(delay) = calculate_trigger_delay(&group_key) {
-        midi_log!("Delaying macro trigger by {:?}", delay);
+        log::debug!("Delaying macro trigger by {:?}", delay);
         tokio::time::sleep(delay).await;
     }
 
     // If a newer session started while we were waiting, bail out
     if !is_current_session(&group_key, session_id) {
-        midi_log!("Session outdated for group {}, skipping trigger handling", group_key);
+        log::debug!("Session outdated for group {}, skipping trigger handling", group_key);
+        emit_macro_complete(&app_handle, "macro-complete", &macro_config, started_at, true);
         return;
     }
 
-    midi_log!("Macro triggered: {} (timeout: {:?}ms)", 
+    log::info!("Macro triggered: {} (timeout: {:?}ms)", 
         macro_config.name, macro_config.timeout);
 
-    // 4) Execute before actions only once per active session (until after_actions run)
-    if try_mark_before_started(&group_key) {
+    // Set while this flow's before_actions run, so a timeout can unblock the waiters
+    let running_before = std::sync::atomic::AtomicBool::new(false);
+    let flow = run_before_and_main(&macro_config, trigger, session_id, &running_before, &app_handle);
+    let completed = match macro_config.max_flow_ms.filter(|&ms| ms > 0) {
+        Some(max_flow_ms) => match tokio::time::timeout(std::time::Duration::from_millis(max_flow_ms), flow).await {
+            Ok(completed) => completed,
+            Err(_) => {
+                handle_flow_timeout(&macro_config, max_flow_ms, &app_handle);
+                emit_macro_complete(&app_handle, "macro-complete", &macro_config, started_at, true);
+                // Close the abandoned flow: past its before_actions it ends as usual, with the
+                // after_actions on their timer; otherwise the next trigger starts over
+                match after_actions_timeout(&macro_config).filter(|_| !running_before.into_inner()) {
+                    Some(timeout) => schedule_after_actions(macro_config, app_handle, timeout, session_id).await,
+                    None => forget_before_state(&group_key),
+                }
+                return;
+            },
+        },
+        None => flow.await,
+    };
+    let Some(completed) = completed else {
+        emit_macro_complete(&app_handle, "macro-complete", &macro_config, started_at, true);
+        return;
+    };
+    emit_macro_complete(&app_handle, "macro-complete", &macro_config, started_at, !completed);
+
+    // A momentary macro's note may have been released while its actions were still running
+    if macro_config.momentary == Some(true) && !lock_or_recover(&APP_STATE.held_momentary_macros).contains(&macro_config.id) {
+        cleanup_mouse_state_for_macro(&macro_config.id);
+    }
+
+    // 6) Schedule/Reset after-actions timer based on timeout
+    if let Some(timeout) = after_actions_timeout(&macro_config) {
+        schedule_after_actions(macro_config, app_handle, timeout, session_id).await;
+    }
+}
+
+// When a flow's after_actions run. Macros with after_actions but no timeout of their own
+// use the global default_timeout; None means nothing is scheduled.
+fn after_actions_timeout(macro_config: &MacroConfig) -> Option<u32> {
+    let has_after_actions = macro_config.after_actions.as_ref().is_some_and(|a| !a.is_empty());
+    macro_config.timeout.or_else(|| {
+        has_after_actions.then(|| lock_or_recover(&APP_STATE.global_settings).default_timeout)
+    })
+}
+
+// End the group's before-actions session without after_actions: wake the triggers waiting
+// on its before_actions and let the next trigger run them again
+fn forget_before_state(group_key: &str) {
+    if let Some(notifier) = take_before_notifier(group_key) {
+        notifier.notify_waiters();
+    }
+    lock_or_recover(&APP_STATE.before_action_states).remove(group_key);
+}
+
+// Steps 4-5 of handle_macro_trigger. Returns whether the main actions completed,
+// or None if a newer session took over while waiting for another flow's before_actions.
+async fn run_before_and_main<R: Runtime>(
+    macro_config: &MacroConfig,
+    trigger: Option<MidiData>,
+    session_id: u64,
+    running_before: &std::sync::atomic::AtomicBool,
+    app_handle: &AppHandle<R>,
+) -> Option<bool> {
+    let group_key = macro_config.groupId.as_ref()
+        .unwrap_or(&macro_config.id)
+        .clone();
+
+    // 4) Execute before actions only once per active session (until after_actions run),
+    //    or also on every before_every_n-th trigger
+    let before_in_progress = get_before_notifier(&group_key).is_some();
+    if try_mark_before_started(&group_key, macro_config.before_every_n, before_in_progress) {
         // Publish a notifier so subsequent triggers wait for before completion
         let notify = std::sync::Arc::new(tokio::sync::Notify::new());
         set_before_notifier(&group_key, notify.clone());
-        execute_before_actions(&macro_config, &app_handle).await;
+        running_before.store(true, std::sync::atomic::Ordering::SeqCst);
+        execute_before_actions(macro_config, app_handle).await;
+        running_before.store(false, std::sync::atomic::Ordering::SeqCst);
         // Notify all waiters that before_actions finished (including any Delay)
         if let Some(notifier) = take_before_notifier(&group_key) {
             notifier.notify_waiters();
@@ -850,17 +3392,75 @@ async fn handle_macro_trigger<R: Runtime>(
         notifier.notified().await;
         // Re-check session still current after waiting
         if !is_current_session(&group_key, session_id) {
-            midi_log!("Session outdated after waiting for before on group {}, skipping", group_key);
-            return;
+            log::debug!("Session outdated after waiting for before on group {}, skipping", group_key);
+            return None;
         }
     }
 
-    // 5) Execute main actions for this trigger
-    execute_main_actions(&macro_config, &app_handle).await;
+    // 5) Execute main actions for this trigger, after dropping stale modifiers that
+    //    would otherwise combine with its input
+    sync_modifiers(false);
+    Some(execute_main_actions(macro_config, trigger, session_id, app_handle).await)
+}
 
-    // 6) Schedule/Reset after-actions timer based on timeout
-    if let Some(timeout) = macro_config.timeout {
-        schedule_after_actions(macro_config, app_handle, timeout, session_id).await;
+const MACRO_TIMEOUT_EVENT: &str = "macro-timeout";
+
+#[derive(Debug, Clone, Serialize)]
+struct MacroTimeoutEvent {
+    macro_id: String,
+    macro_name: String,
+    group_id: Option<String>,
+    max_flow_ms: u64,
+}
+
+// The flow's future has been dropped, so its remaining actions never run. Release what the
+// macro holds and report it; the caller then closes the group's session.
+fn handle_flow_timeout<R: Runtime>(
+    macro_config: &MacroConfig,
+    max_flow_ms: u64,
+    app_handle: &AppHandle<R>,
+) {
+    log::warn!("Macro {} exceeded max_flow_ms ({}ms); abandoning its remaining actions", macro_config.name, max_flow_ms);
+    cleanup_mouse_state_for_macro(&macro_config.id);
+
+    let payload = MacroTimeoutEvent {
+        macro_id: macro_config.id.clone(),
+        macro_name: macro_config.name.clone(),
+        group_id: macro_config.groupId.clone(),
+        max_flow_ms,
+    };
+    if let Err(e) = app_handle.emit(MACRO_TIMEOUT_EVENT, payload) {
+        log::error!("Failed to emit {}: {}", MACRO_TIMEOUT_EVENT, e);
+    }
+}
+
+// Payload for macro-complete / after-actions-complete, mirroring macro-trigger
+#[derive(Debug, Clone, Serialize)]
+struct MacroCompleteEvent {
+    macro_id: String,
+    macro_name: String,
+    group_id: Option<String>,
+    duration_ms: u64,
+    // True when a newer trigger of the same group cut this flow short
+    aborted: bool,
+}
+
+fn emit_macro_complete<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    event: &str,
+    macro_config: &MacroConfig,
+    started_at: std::time::Instant,
+    aborted: bool,
+) {
+    let payload = MacroCompleteEvent {
+        macro_id: macro_config.id.clone(),
+        macro_name: macro_config.name.clone(),
+        group_id: macro_config.groupId.clone(),
+        duration_ms: started_at.elapsed().as_millis() as u64,
+        aborted,
+    };
+    if let Err(e) = app_handle.emit(event, payload) {
+        log::error!("Failed to emit {}: {}", event, e);
     }
 }
 
@@ -869,8 +3469,8 @@ async fn execute_pending_after_actions<R: Runtime>(
     app_handle: &AppHandle<R>,
 ) {
     let macros_to_execute = {
-        let mut active_macros = APP_STATE.active_macros.lock().unwrap();
-        let registered_macros = APP_STATE.registered_macros.lock().unwrap();
+        let mut active_macros = lock_or_recover(&APP_STATE.active_macros);
+        let registered_macros = lock_or_recover(&APP_STATE.registered_macros);
         
         let mut result = Vec::new();
         let mut keys_to_remove = Vec::new();
@@ -904,41 +3504,29 @@ async fn execute_pending_after_actions<R: Runtime>(
     // Execute after actions
     for (key, macro_config) in macros_to_execute {
         if let Some(after_actions) = &macro_config.after_actions {
-            midi_log!("Executing pending after_actions for: {}", key);
+            log::debug!("Executing pending after_actions for: {}", key);
             
-            for (i, action) in after_actions.iter().enumerate() {
-                if let ActionType::Delay = action.action_type {
-                    if let Some(duration_ms) = action.action_params.duration {
-                        tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms as u64)).await;
-                    }
-                } else {
-                    if let Err(e) = execute_action_safe(
-                        action.action_type.clone(),
-                        action.action_params.clone(),
-                        Some(app_handle.clone())
-                    ).await {
-                        eprintln!("Error executing after action {}: {}", i, e);
-                    }
-                }
-            }
+            // Runs to completion even if this group is retriggered meanwhile: it's closing out
+            let context = ActionContext::new(&macro_config, "after", None);
+            run_actions(after_actions, &context, app_handle).await;
             
             // Clean up before_action_state and any notifier
-            APP_STATE.before_action_states.lock().unwrap().remove(&key);
-            APP_STATE.before_notifiers.lock().unwrap().remove(&key);
+            lock_or_recover(&APP_STATE.before_action_states).remove(&key);
+            lock_or_recover(&APP_STATE.before_notifiers).remove(&key);
         }
     }
 }
 
 fn cancel_existing_macro_task(group_key: &str) {
-    let mut active_macros = APP_STATE.active_macros.lock().unwrap();
+    let mut active_macros = lock_or_recover(&APP_STATE.active_macros);
     if let Some(active_macro) = active_macros.remove(group_key) {
         active_macro.abort_handle.abort();
-        midi_log!("Cancelled existing task for macro group: {}", group_key);
+        log::debug!("Cancelled existing task for macro group: {}", group_key);
     }
 }
 
 fn should_execute_before_actions(state_key: &str) -> bool {
-    let before_action_states = APP_STATE.before_action_states.lock().unwrap();
+    let before_action_states = lock_or_recover(&APP_STATE.before_action_states);
     !before_action_states.contains_key(state_key)
 }
 
@@ -951,118 +3539,975 @@ async fn execute_before_actions<R: Runtime>(
             return;
         }
         
-        midi_log!("Executing before actions for macro: {}", macro_config.name);
+        log::debug!("Executing before actions for macro: {}", macro_config.name);
         
-        for (i, action) in before_actions.iter().enumerate() {
-            if let ActionType::Delay = action.action_type {
-                if let Some(duration_ms) = action.action_params.duration {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms as u64)).await;
-                }
-            } else {
-                if let Err(e) = execute_action_safe(
-                    action.action_type.clone(),
-                    action.action_params.clone(),
-                    Some(app_handle.clone())
-                ).await {
-                    eprintln!("Error executing before action {}: {}", i, e);
-                }
-            }
-        }
+        // Not tied to the session: before_actions always run to completion, so a retrigger
+        // can't cut them off between a press and its release
+        let context = ActionContext::new(macro_config, "before", None);
+        run_actions(before_actions, &context, app_handle).await;
         
     // Marking moved to try_mark_before_started to avoid races
     }
 }
 
-async fn execute_main_actions<R: Runtime>(
-    macro_config: &MacroConfig,
-    app_handle: &AppHandle<R>,
-) {
-    for (i, action) in macro_config.actions.iter().enumerate() {
-        midi_log!("Executing main action {} of type {:?}", i, action.action_type);
-        
-        if let ActionType::Delay = action.action_type {
-            if let Some(duration_ms) = action.action_params.duration {
-                tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms as u64)).await;
-            }
-        } else {
-            if let Err(e) = execute_action_safe(
-                action.action_type.clone(),
-                action.action_params.clone(),
-                Some(app_handle.clone())
-            ).await {
-                eprintln!("Error executing main action {}: {}", i, e);
-            }
-        }
+// Steepness of the exponential velocity curve (higher = softer hits matter less)
+const VELOCITY_EXPONENTIAL_STEEPNESS: f64 = 3.0;
+
+// Roll the shared RNG for an action's probability; actions without one always run
+fn passes_probability(probability: Option<f32>) -> bool {
+    match probability {
+        None => true,
+        Some(p) if p >= 1.0 => true,
+        Some(p) => lock_or_recover(&APP_STATE.rng).gen::<f32>() < p,
     }
 }
 
-async fn schedule_after_actions<R: Runtime>(
-    macro_config: MacroConfig,
-    app_handle: AppHandle<R>,
-    timeout_ms: u32,
-    session_id: u64,
-) {
-    let task_key = macro_config.groupId.as_ref()
-        .unwrap_or(&macro_config.id)
-        .clone();
-    
-    let has_after_actions = macro_config.after_actions
-        .as_ref()
-        .map_or(false, |a| !a.is_empty());
-    
-    let task_key_for_closure = task_key.clone();
-    let task_key_for_check = task_key.clone();
-    let abort_handle = tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_millis(timeout_ms as u64)).await;
+// Command to make randomized behavior (probability, delay jitter) repeatable by seeding
+// the shared RNG; None goes back to an unpredictable seed
+#[tauri::command]
+fn set_random_seed(seed: Option<u64>) {
+    *lock_or_recover(&APP_STATE.rng) = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    log::info!("Random seed set to {:?}", seed);
+}
 
-        // If a new session started, skip executing after-actions
-        if !is_current_session(&task_key_for_check, session_id) {
-            midi_log!("After-actions skipped due to newer session for group {}", task_key_for_check);
-            return;
-        }
-        
-        if has_after_actions {
-            if let Some(after_actions) = &macro_config.after_actions {
-                for (i, action) in after_actions.iter().enumerate() {
-                    if let ActionType::Delay = action.action_type {
-                        if let Some(duration_ms) = action.action_params.duration {
-                            tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms as u64)).await;
-                        }
-                    } else {
-                        if let Err(e) = execute_action_safe(
-                            action.action_type.clone(),
-                            action.action_params.clone(),
-                            Some(app_handle.clone())
-                        ).await {
-                            eprintln!("Error executing after action {}: {}", i, e);
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Clean up
-        APP_STATE.active_macros.lock().unwrap().remove(&task_key_for_closure);
-    APP_STATE.before_action_states.lock().unwrap().remove(&task_key_for_closure);
-    APP_STATE.before_notifiers.lock().unwrap().remove(&task_key_for_closure);
-    }).abort_handle();
-    
-    // Store the task
-    APP_STATE.active_macros.lock().unwrap().insert(
-        task_key,
-        ActiveMacro {
-            abort_handle,
-            last_triggered: std::time::Instant::now(),
-        }
-    );
+// Return the action's params with velocity scaling applied for NoteOn triggers
+fn apply_velocity_scaling(action: &MacroAction, trigger: Option<&MidiData>) -> ActionParams {
+    let mut params = action.action_params.clone();
+    let (scaling, midi_data) = match (&action.velocity_scaling, trigger) {
+        (Some(scaling), Some(midi_data)) if midi_data.message_type == MidiMessageType::NoteOn => {
+            (scaling, midi_data)
+        },
+        _ => return params,
+    };
+
+    let normalized = midi_data.data2 as f64 / 127.0;
+    let curved = match scaling.curve {
+        ScalingCurve::Linear => normalized,
+        ScalingCurve::Exponential => {
+            ((VELOCITY_EXPONENTIAL_STEEPNESS * normalized).exp() - 1.0)
+                / (VELOCITY_EXPONENTIAL_STEEPNESS.exp() - 1.0)
+        },
+    };
+    let value = scaling.min + (scaling.max - scaling.min) * curved;
+
+    match scaling.target {
+        ScalingTarget::Amount => params.amount = Some(value.round() as i32),
+        ScalingTarget::Duration => params.duration = Some(value.max(0.0).round() as u32),
+    }
+    log::debug!("Velocity {} scaled {:?} to {}", midi_data.data2, scaling.target, value);
+    params
 }
 
-fn emit_midi_event<R: Runtime>(
-    midi_data: &MidiData,
-    timestamp: TimestampMs,
-    app_handle: &AppHandle<R>,
-) {
-    let type_name = match midi_data.message_type {
+// Multiplier for this group's scroll amounts based on how soon after its previous main
+// actions it was triggered: up to scroll_acceleration for back-to-back triggers, falling
+// off along scroll_acceleration_curve to 1 at scroll_acceleration_window_ms
+fn scroll_acceleration(group_key: &str) -> f64 {
+    let now = std::time::Instant::now();
+    let previous = lock_or_recover(&APP_STATE.last_scroll_triggers).insert(group_key.to_string(), now);
+    let (max, window_ms, curve) = {
+        let settings = lock_or_recover(&APP_STATE.global_settings);
+        (settings.scroll_acceleration, settings.scroll_acceleration_window_ms, settings.scroll_acceleration_curve)
+    };
+    let Some(previous) = previous else { return 1.0 };
+    if max <= 1.0 || window_ms == 0 {
+        return 1.0;
+    }
+    let elapsed_ms = now.duration_since(previous).as_secs_f64() * 1000.0;
+    let speed = (1.0 - elapsed_ms / window_ms as f64).max(0.0);
+    1.0 + (max - 1.0) * speed.powf(curve.max(0.1))
+}
+
+// Apply scroll acceleration to scroll-* MouseClicks and ScrollSmooth; other actions are untouched
+fn accelerate_scroll(action_type: &ActionType, mut params: ActionParams, multiplier: f64) -> ActionParams {
+    if multiplier <= 1.0 {
+        return params;
+    }
+    let base = match action_type {
+        ActionType::MouseClick if params.button.as_deref().is_some_and(is_scroll_button) => {
+            params.amount.unwrap_or(DEFAULT_SCROLL_CLICK_AMOUNT)
+        },
+        ActionType::ScrollSmooth => match params.amount {
+            Some(amount) => amount,
+            None => return params,
+        },
+        _ => return params,
+    };
+    let accelerated = (base as f64 * multiplier).round() as i32;
+    log::debug!("Scroll acceleration x{:.2}: {} -> {}", multiplier, base, accelerated);
+    params.amount = Some(accelerated);
+    params
+}
+
+// Latch a modifier for the next key-producing action, or unlatch it if it's already latched
+fn toggle_latched_modifier(key: String) {
+    let mut latched = lock_or_recover(&APP_STATE.latched_modifiers);
+    if let Some(position) = latched.iter().position(|k| k.eq_ignore_ascii_case(&key)) {
+        latched.remove(position);
+        log::info!("Unlatched modifier {}", key);
+    } else {
+        log::info!("Latched modifier {}", key);
+        latched.push(key);
+    }
+}
+
+// Actions that type something, and so consume latched modifiers
+fn produces_keys(action_type: &ActionType) -> bool {
+    matches!(
+        action_type,
+        ActionType::KeyPress | ActionType::KeyTap | ActionType::KeyCombination | ActionType::KeyHoldFor
+            | ActionType::TypeText | ActionType::RawKey
+    )
+}
+
+// --- MIDI clock ------------------------------------------------------------------
+const MIDI_CLOCK_PPQN: f64 = 24.0;
+// Ticks averaged for the tempo: two beats
+const MIDI_CLOCK_WINDOW: usize = 48;
+// Fewest ticks (a quarter of a beat) before a tempo is reported
+const MIDI_CLOCK_MIN_TICKS: usize = 7;
+// The clock counts as stopped when no tick arrived for this long (below 5 BPM)
+const MIDI_CLOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn track_midi_clock(midi_data: &MidiData) {
+    match midi_data.status {
+        0xF8 => {
+            let mut ticks = lock_or_recover(&APP_STATE.midi_clock);
+            let now = std::time::Instant::now();
+            if ticks.back().is_some_and(|last| now.duration_since(*last) > MIDI_CLOCK_TIMEOUT) {
+                ticks.clear();
+            }
+            if ticks.len() >= MIDI_CLOCK_WINDOW {
+                ticks.pop_front();
+            }
+            ticks.push_back(now);
+        },
+        // Stop
+        0xFC => lock_or_recover(&APP_STATE.midi_clock).clear(),
+        _ => {},
+    }
+}
+
+// Tempo of the incoming MIDI clock, if one is running
+fn detected_bpm() -> Option<f64> {
+    let ticks = lock_or_recover(&APP_STATE.midi_clock);
+    let (first, last) = (ticks.front()?, ticks.back()?);
+    if ticks.len() < MIDI_CLOCK_MIN_TICKS || last.elapsed() > MIDI_CLOCK_TIMEOUT {
+        return None;
+    }
+    let tick_secs = last.duration_since(*first).as_secs_f64() / (ticks.len() - 1) as f64;
+    (tick_secs > 0.0).then(|| 60.0 / (tick_secs * MIDI_CLOCK_PPQN))
+}
+
+// Length of a note value in quarter notes: "1/4" = 1, "1/8d" (or "1/8.") = 0.75, "1/8t" = 1/3
+fn parse_note_value(note_value: &str) -> Result<f64, String> {
+    let value = note_value.trim().to_lowercase();
+    let (fraction, modifier) = match value.strip_suffix('d').or_else(|| value.strip_suffix('.')) {
+        Some(rest) => (rest, 1.5),
+        None => match value.strip_suffix('t') {
+            Some(rest) => (rest, 2.0 / 3.0),
+            None => (value.as_str(), 1.0),
+        },
+    };
+    let (numerator, denominator) = fraction.split_once('/').unwrap_or((fraction, "1"));
+    let numerator: f64 = numerator.trim().parse().map_err(|_| format!("Invalid note value: {}", note_value))?;
+    let denominator: f64 = denominator.trim().parse().map_err(|_| format!("Invalid note value: {}", note_value))?;
+    if numerator <= 0.0 || denominator <= 0.0 {
+        return Err(format!("Invalid note value: {}", note_value));
+    }
+    Ok(4.0 * numerator / denominator * modifier)
+}
+
+// Milliseconds of a Delay's note_value at the MIDI clock's tempo; None without note_value or clock
+fn note_value_ms(params: &ActionParams) -> Option<i64> {
+    let quarters = parse_note_value(params.note_value.as_deref()?).ok()?;
+    let bpm = detected_bpm()?;
+    Some((quarters * 60_000.0 / bpm).round() as i64)
+}
+
+// Resolve a Delay action's sleep time: the note_value at the current MIDI clock tempo (or
+// duration when there is no clock) plus a random offset in -jitter..=+jitter
+fn delay_duration(params: &ActionParams) -> Option<std::time::Duration> {
+    let duration_ms = match note_value_ms(params) {
+        Some(ms) => ms,
+        None => params.duration? as i64,
+    };
+    let jitter_ms = params.jitter_ms.unwrap_or(0) as i64;
+    let offset = if jitter_ms > 0 {
+        lock_or_recover(&APP_STATE.rng).gen_range(-jitter_ms..=jitter_ms)
+    } else {
+        0
+    };
+    Some(std::time::Duration::from_millis((duration_ms + offset).max(0) as u64))
+}
+
+// Describes which macro/phase a list of actions belongs to, threaded through run_actions
+#[derive(Debug, Clone)]
+struct ActionContext {
+    macro_id: String,
+    group_key: String,
+    phase: &'static str,
+    // MIDI message that triggered the macro, if any (used for velocity scaling)
+    trigger: Option<MidiData>,
+    // Group session the actions run under; once a newer session starts the rest is skipped
+    session_id: Option<u64>,
+    // How many RunMacro actions deep these actions are nested
+    depth: u32,
+    // Record inverses of executed actions for undo_last_macro (main actions only)
+    record_undo: bool,
+    // Factor for scroll amounts, above 1 when the group is being triggered rapidly
+    scroll_multiplier: f64,
+}
+
+// Deepest RunMacro nesting allowed, so a macro that (indirectly) runs itself stops
+const MAX_MACRO_DEPTH: u32 = 8;
+
+impl ActionContext {
+    fn new(macro_config: &MacroConfig, phase: &'static str, session_id: Option<u64>) -> Self {
+        Self {
+            macro_id: macro_config.id.clone(),
+            group_key: macro_config.groupId.clone().unwrap_or_else(|| macro_config.id.clone()),
+            phase,
+            trigger: None,
+            session_id,
+            depth: 0,
+            record_undo: false,
+            scroll_multiplier: 1.0,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.session_id.is_some_and(|id| !is_current_session(&self.group_key, id))
+    }
+}
+
+// Single place where action lists are executed, so every phase (before/main/after)
+// handles Delay, scaling and session cancellation the same way. Delays are awaited
+// here rather than in execute_action_with so they don't block a thread.
+// Returns false if the actions were cut short by a newer session, a WaitForPixel timeout
+// or a KeyTap or ScrollSmooth that failed partway.
+async fn run_actions<R: Runtime>(
+    actions: &[MacroAction],
+    context: &ActionContext,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    for (i, action) in actions.iter().enumerate() {
+        if context.is_cancelled() {
+            log::debug!("Stopping {} actions of {}: group {} was retriggered", context.phase, context.macro_id, context.group_key);
+            return false;
+        }
+        if let Some(condition) = &action.condition {
+            let active_app = frontmost_app_name();
+            if !condition_matches(condition, active_app.as_deref()) {
+                log::info!(
+                    "Skipping {} action {} of {}: active app {:?} doesn't match {:?}",
+                    context.phase, i, context.macro_id, active_app, condition.active_app
+                );
+                continue;
+            }
+        }
+        if !passes_probability(action.action_params.probability) {
+            log::debug!("Skipping {} action {} of {}: probability roll failed", context.phase, i, context.macro_id);
+            continue;
+        }
+        log::debug!("Executing {} action {} of type {:?}", context.phase, i, action.action_type);
+        let params = apply_velocity_scaling(action, context.trigger.as_ref());
+        let params = accelerate_scroll(&action.action_type, params, context.scroll_multiplier);
+
+        if produces_keys(&action.action_type) {
+            let latched = std::mem::take(&mut *lock_or_recover(&APP_STATE.latched_modifiers));
+            if !latched.is_empty() {
+                // Already rolled, checked and scaled, so the nested run gets the plain action
+                let latched_action = MacroAction {
+                    action_type: action.action_type.clone(),
+                    action_params: ActionParams { probability: None, ..params },
+                    velocity_scaling: None,
+                    condition: None,
+                };
+                log::debug!("Applying latched modifiers {:?} to {} action {}", latched, context.phase, i);
+                let wrapped = ActionParams {
+                    modifiers: Some(latched),
+                    actions: Some(vec![latched_action]),
+                    ..Default::default()
+                };
+                if !run_with_modifiers(&wrapped, context, i, app_handle).await {
+                    return false;
+                }
+                continue;
+            }
+        }
+        
+        // Each arm gives the action's result for the history and whether the list goes on
+        let (result, keep_going) = match action.action_type {
+            ActionType::Delay => {
+                if let Some(delay) = delay_duration(&params) {
+                    tokio::time::sleep(delay).await;
+                }
+                (Ok(()), true)
+            },
+            ActionType::KeyTap => finished(tap_key_repeatedly(&params, context, i, app_handle).await),
+            ActionType::KeyCombination if params.hold_duration_ms.is_some_and(|ms| ms > 0) => {
+                let keys = params.keys.as_deref().unwrap_or_default();
+                let hold = std::time::Duration::from_millis(params.hold_duration_ms.unwrap_or(0) as u64);
+                finished(hold_keys(keys, hold, ActionType::KeyCombination, context, i, app_handle).await)
+            },
+            ActionType::KeyHoldFor => match params.key.clone() {
+                Some(key) => {
+                    let hold = std::time::Duration::from_millis(params.duration.unwrap_or(0) as u64);
+                    finished(hold_keys(&[key], hold, ActionType::KeyHoldFor, context, i, app_handle).await)
+                },
+                None => {
+                    let error = "Missing key parameter for KeyHoldFor".to_string();
+                    report_action_error(app_handle, context, i, ActionType::KeyHoldFor, error.clone());
+                    (Err(error), true)
+                },
+            },
+            ActionType::WithModifiers => finished(run_with_modifiers(&params, context, i, app_handle).await),
+            ActionType::ScrollSmooth => finished(scroll_smoothly(&params, context, i, app_handle).await),
+            ActionType::RunMacro => finished(run_nested_macro(&params, context, i, app_handle).await),
+            ActionType::KeepAwake => finished(keep_awake(&params, context, i, app_handle).await),
+            ActionType::WaitForPixel => match wait_for_pixel(&params, context).await {
+                Ok(completed) => finished(completed),
+                Err(e) => {
+                    // The rest of the list depends on the awaited screen state, so don't run it
+                    report_action_error(app_handle, context, i, ActionType::WaitForPixel, e.clone());
+                    (Err(e), false)
+                },
+            },
+            _ => {
+                let undo_params = context.record_undo.then(|| params.clone());
+                let token = ActionToken::new();
+                let result = execute_action_timed_with(action.action_type.clone(), params, context, app_handle, token.clone()).await;
+                match &result {
+                    Ok(()) => {
+                        if let Some(inverse) = undo_params.and_then(|p| inverse_action(&action.action_type, &p, &token)) {
+                            push_undo(&context.group_key, inverse);
+                        }
+                    },
+                    Err(e) => report_action_error(app_handle, context, i, action.action_type.clone(), e.clone()),
+                }
+                (result, true)
+            },
+        };
+        record_action_history(context, action.action_type.clone(), &result);
+        if !keep_going {
+            return false;
+        }
+    }
+    true
+}
+
+// History result of an action run_actions carries out itself; one cut short stops the list
+fn finished(completed: bool) -> (Result<(), String>, bool) {
+    if completed {
+        (Ok(()), true)
+    } else {
+        (Err("Stopped before finishing".to_string()), false)
+    }
+}
+
+// Oldest entries are dropped beyond this, so repeating macros can't grow the stack forever
+const MAX_UNDO_ENTRIES: usize = 256;
+
+// The action that reverses one that just ran; mouse moves go back to where the cursor was
+// when the move started, as recorded on its token. Releases aren't undone, since pressing
+// again would leave a key or button held that no macro owns and nothing would release.
+// None for actions that can't be undone.
+fn inverse_action(action_type: &ActionType, params: &ActionParams, token: &ActionToken) -> Option<(ActionType, ActionParams)> {
+    match action_type {
+        ActionType::KeyPress if params.hold == Some(true) => {
+            Some((ActionType::KeyRelease, ActionParams { key: params.key.clone(), ..Default::default() }))
+        },
+        ActionType::MouseClick if params.hold == Some(true) && !params.button.as_deref().is_some_and(is_scroll_button) => {
+            Some((ActionType::MouseRelease, ActionParams { button: params.button.clone(), ..Default::default() }))
+        },
+        // MoveAndClick's click can't be taken back, but its move can
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick => {
+            let (x, y) = token.cursor_before()?;
+            Some((ActionType::MouseMove, ActionParams { x: Some(x), y: Some(y), ..Default::default() }))
+        },
+        _ => {
+            log::debug!("{:?} can't be undone; it won't be reverted by undo_last_macro", action_type);
+            None
+        },
+    }
+}
+
+fn push_undo(group_key: &str, inverse: (ActionType, ActionParams)) {
+    let mut undo_stacks = lock_or_recover(&APP_STATE.undo_stacks);
+    let stack = undo_stacks.entry(group_key.to_string()).or_default();
+    if stack.len() >= MAX_UNDO_ENTRIES {
+        stack.remove(0);
+    }
+    stack.push(inverse);
+}
+
+// Command to revert the reversible main actions of a group's last run (the macro id for
+// ungrouped macros), newest first. Returns how many actions were reverted.
+#[tauri::command]
+async fn undo_last_macro<R: Runtime>(app_handle: AppHandle<R>, group_id: String) -> Result<usize, String> {
+    let inverses = lock_or_recover(&APP_STATE.undo_stacks)
+        .remove(&group_id)
+        .ok_or_else(|| format!("Nothing to undo for {}", group_id))?;
+    log::info!("Undoing {} actions of {}", inverses.len(), group_id);
+
+    let mut undone = 0;
+    for (action_type, params) in inverses.into_iter().rev() {
+        match execute_action_safe(action_type.clone(), params, None, ActionToken::new(), Some(app_handle.clone())).await {
+            Ok(()) => undone += 1,
+            Err(e) => log::error!("Failed to undo {:?} for {}: {}", action_type, group_id, e),
+        }
+    }
+    Ok(undone)
+}
+
+const MACRO_ACTION_ERROR_EVENT: &str = "macro-action-error";
+
+#[derive(Debug, Clone, Serialize)]
+struct MacroActionErrorEvent {
+    macro_id: String,
+    phase: String,
+    action_index: usize,
+    action_type: ActionType,
+    error: String,
+}
+
+// Log a failed action and tell the frontend through a macro-action-error event
+fn report_action_error<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    context: &ActionContext,
+    index: usize,
+    action_type: ActionType,
+    error: String,
+) {
+    log::error!("Error executing {} action {} ({:?}) of {}: {}", context.phase, index, action_type, context.macro_id, error);
+    let event = MacroActionErrorEvent {
+        macro_id: context.macro_id.clone(),
+        phase: context.phase.to_string(),
+        action_index: index,
+        action_type,
+        error,
+    };
+    if let Err(e) = app_handle.emit(MACRO_ACTION_ERROR_EVENT, event) {
+        log::error!("Failed to emit {}: {}", MACRO_ACTION_ERROR_EVENT, e);
+    }
+}
+
+// Run one action of a macro, giving up once it has run for GlobalSettings.action_timeout_ms
+// (time spent queued behind the macro's other input doesn't count). A timed-out action may
+// still finish in the background, so its input thread is replaced and everything the macro
+// holds is released to avoid stuck keys; the caller logs the error and moves on.
+async fn execute_action_timed<R: Runtime>(
+    action_type: ActionType,
+    params: ActionParams,
+    context: &ActionContext,
+    app_handle: &AppHandle<R>,
+) -> Result<(), String> {
+    execute_action_timed_with(action_type, params, context, app_handle, ActionToken::new()).await
+}
+
+// execute_action_timed with a token the caller keeps, e.g. to read what the action recorded
+async fn execute_action_timed_with<R: Runtime>(
+    action_type: ActionType,
+    params: ActionParams,
+    context: &ActionContext,
+    app_handle: &AppHandle<R>,
+    token: Arc<ActionToken>,
+) -> Result<(), String> {
+    let timeout_ms = lock_or_recover(&APP_STATE.global_settings).action_timeout_ms;
+    let execution = execute_action_safe(action_type, params, Some(context.macro_id.clone()), token.clone(), Some(app_handle.clone()));
+    let result = if timeout_ms == 0 {
+        execution.await
+    } else {
+        tokio::pin!(execution);
+        let finished = tokio::select! {
+            result = &mut execution => Some(result),
+            _ = token.started.notified() => None,
+        };
+        match finished {
+            Some(result) => result,
+            None => match tokio::time::timeout(std::time::Duration::from_millis(timeout_ms), &mut execution).await {
+                Ok(result) => result,
+                Err(_) => {
+                    log::warn!("Action of {} exceeded {}ms; releasing its held inputs", context.macro_id, timeout_ms);
+                    token.cancel();
+                    #[cfg(not(target_os = "macos"))]
+                    restart_input_thread(Some(&context.macro_id));
+                    cleanup_mouse_state_for_macro(&context.macro_id);
+                    Err(format!("Timed out after {}ms", timeout_ms))
+                },
+            },
+        }
+    };
+    result
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ActionHistoryEntry {
+    timestamp_ms: u64, // Milliseconds since the Unix epoch, when the action finished
+    macro_id: String,
+    phase: &'static str,
+    action_type: ActionType,
+    error: Option<String>, // None if the action succeeded
+}
+
+// Called by run_actions once for every action it runs, including the Delay, wait and hold
+// steps it handles without execute_action_timed
+fn record_action_history(context: &ActionContext, action_type: ActionType, result: &Result<(), String>) {
+    let capacity = lock_or_recover(&APP_STATE.global_settings).action_history_size;
+    let mut history = lock_or_recover(&APP_STATE.action_history);
+    if capacity == 0 {
+        history.clear();
+        return;
+    }
+    while history.len() >= capacity {
+        history.pop_front();
+    }
+    history.push_back(ActionHistoryEntry {
+        timestamp_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        macro_id: context.macro_id.clone(),
+        phase: context.phase,
+        action_type,
+        error: result.as_ref().err().cloned(),
+    });
+}
+
+// Command to list the most recently executed macro actions, oldest first
+#[tauri::command]
+fn get_action_history() -> Vec<ActionHistoryEntry> {
+    lock_or_recover(&APP_STATE.action_history).iter().cloned().collect()
+}
+
+// Press the modifiers, run the nested actions, then release the modifiers again even if
+// an inner action failed or the run was cancelled. Modifiers that were already held
+// (e.g. by another macro) are left alone. Returns false if the inner run was cut short.
+async fn run_with_modifiers<R: Runtime>(
+    params: &ActionParams,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let modifiers: Vec<String> = {
+        let key_state = lock_or_recover(&APP_STATE.key_state);
+        params.modifiers.iter().flatten()
+            .filter(|name| string_to_key(name).is_some_and(|key| !key_state.contains_key(&key)))
+            .cloned()
+            .collect()
+    };
+
+    for modifier in &modifiers {
+        let press = ActionParams { key: Some(modifier.clone()), hold: Some(true), ..Default::default() };
+        if let Err(e) = execute_action_timed(ActionType::KeyPress, press, context, app_handle).await {
+            report_action_error(app_handle, context, index, ActionType::WithModifiers, format!("Failed to press modifier {}: {}", modifier, e));
+        }
+    }
+
+    let actions = params.actions.as_deref().unwrap_or_default();
+    let completed = Box::pin(run_actions(actions, context, app_handle)).await;
+
+    for modifier in modifiers.iter().rev() {
+        let release = ActionParams { key: Some(modifier.clone()), ..Default::default() };
+        if let Err(e) = execute_action_timed(ActionType::KeyRelease, release, context, app_handle).await {
+            report_action_error(app_handle, context, index, ActionType::WithModifiers, format!("Failed to release modifier {}: {}", modifier, e));
+        }
+    }
+    completed
+}
+
+// How often long in-action waits check whether their group was retriggered
+const CANCEL_CHECK_INTERVAL_MS: u64 = 100;
+
+// Sleep for `duration` in short slices so a retrigger interrupts long waits promptly.
+// Returns false if a newer session cancelled the wait.
+async fn sleep_unless_cancelled(duration: std::time::Duration, context: &ActionContext) -> bool {
+    let check = std::time::Duration::from_millis(CANCEL_CHECK_INTERVAL_MS);
+    let deadline = std::time::Instant::now() + duration;
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        tokio::time::sleep(check.min(remaining)).await;
+        if context.is_cancelled() {
+            return false;
+        }
+    }
+}
+
+// Press `keys` in order, keep them down for `hold`, then release them in reverse order
+// (KeyCombination with hold_duration_ms, KeyHoldFor). Keys are pressed as held KeyPresses
+// so they are tracked like any held key; keys something else already holds are left alone.
+// The keys are released even when a newer session cuts the hold short; returns false then.
+async fn hold_keys<R: Runtime>(
+    keys: &[String],
+    hold: std::time::Duration,
+    action_type: ActionType,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let keys: Vec<String> = {
+        let key_state = lock_or_recover(&APP_STATE.key_state);
+        keys.iter()
+            .filter(|name| string_to_key(name).is_some_and(|key| !key_state.contains_key(&key)))
+            .cloned()
+            .collect()
+    };
+
+    let mut pressed = Vec::with_capacity(keys.len());
+    for key in keys {
+        let press = ActionParams { key: Some(key.clone()), hold: Some(true), ..Default::default() };
+        match execute_action_timed(ActionType::KeyPress, press, context, app_handle).await {
+            Ok(()) => pressed.push(key),
+            Err(e) => report_action_error(app_handle, context, index, action_type.clone(), format!("Failed to press {}: {}", key, e)),
+        }
+    }
+
+    log::debug!("{:?}: holding {:?} for {:?}", action_type, pressed, hold);
+    let completed = sleep_unless_cancelled(hold, context).await;
+    if !completed {
+        log::debug!("Releasing {:?} of {} early: group {} was retriggered", pressed, context.macro_id, context.group_key);
+    }
+
+    for key in pressed.iter().rev() {
+        let release = ActionParams { key: Some(key.clone()), ..Default::default() };
+        if let Err(e) = execute_action_timed(ActionType::KeyRelease, release, context, app_handle).await {
+            report_action_error(app_handle, context, index, action_type.clone(), format!("Failed to release {}: {}", key, e));
+        }
+    }
+    completed
+}
+
+// Run another macro's main actions as part of this one. They run under the calling
+// macro's context, so its session, trigger and held-input ownership carry over.
+// Returns false if the nested run was cut short.
+async fn run_nested_macro<R: Runtime>(
+    params: &ActionParams,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let Some(target_id) = params.text.as_deref() else {
+        report_action_error(app_handle, context, index, ActionType::RunMacro, "Missing text (macro id) parameter for RunMacro".to_string());
+        return true;
+    };
+    if context.depth >= MAX_MACRO_DEPTH {
+        let error = format!("Not running macro {}: nesting is deeper than {} (does it run itself?)", target_id, MAX_MACRO_DEPTH);
+        report_action_error(app_handle, context, index, ActionType::RunMacro, error);
+        return true;
+    }
+    let actions = lock_or_recover(&APP_STATE.registered_macros)
+        .iter()
+        .find(|m| m.id == target_id)
+        .map(|m| m.actions.clone());
+    let Some(actions) = actions else {
+        report_action_error(app_handle, context, index, ActionType::RunMacro, format!("Macro {} is not registered", target_id));
+        return true;
+    };
+
+    log::debug!("Running macro {} from {} (depth {})", target_id, context.macro_id, context.depth + 1);
+    let nested = ActionContext { depth: context.depth + 1, ..context.clone() };
+    Box::pin(run_actions(&actions, &nested, app_handle)).await
+}
+
+// Tap the key `amount` times (at least once) with `duration` ms between taps.
+// Returns false if a tap failed or a newer session cancelled the remaining taps.
+async fn tap_key_repeatedly<R: Runtime>(
+    params: &ActionParams,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let count = params.amount.unwrap_or(1).max(1);
+    let interval = std::time::Duration::from_millis(params.duration.unwrap_or(0) as u64);
+    for tap in 0..count {
+        if tap > 0 {
+            tokio::time::sleep(interval).await;
+            if context.is_cancelled() {
+                log::debug!("Stopping KeyTap of {} after {} taps: group {} was retriggered", context.macro_id, tap, context.group_key);
+                return false;
+            }
+        }
+        if let Err(e) = execute_action_timed(ActionType::KeyTap, params.clone(), context, app_handle).await {
+            report_action_error(app_handle, context, index, ActionType::KeyTap, e);
+            return false;
+        }
+    }
+    true
+}
+
+// Spread `amount` scroll clicks over `duration` ms using the MouseDrag step sizing
+// (at least 20 steps, one per ~10ms). Scrolling is click-based, so each step sends the
+// whole clicks accumulated so far and steps with nothing to send are skipped.
+// Returns false if a step failed or a newer session cancelled the remaining steps.
+async fn scroll_smoothly<R: Runtime>(
+    params: &ActionParams,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let total = params.amount.unwrap_or(0);
+    let duration_ms = params.duration.unwrap_or(0);
+    let steps = if duration_ms == 0 { 1 } else { 20.max(duration_ms / 10) };
+    let interval = std::time::Duration::from_millis((duration_ms / steps) as u64);
+
+    let mut sent = 0;
+    for step in 1..=steps {
+        let target = (total as f64 * step as f64 / steps as f64).round() as i32;
+        if target != sent {
+            let step_params = ActionParams { amount: Some(target - sent), ..params.clone() };
+            if let Err(e) = execute_action_timed(ActionType::ScrollSmooth, step_params, context, app_handle).await {
+                report_action_error(app_handle, context, index, ActionType::ScrollSmooth, e);
+                return false;
+            }
+            sent = target;
+        }
+        if step < steps {
+            tokio::time::sleep(interval).await;
+            if context.is_cancelled() {
+                log::debug!("Stopping ScrollSmooth of {} after {} of {} clicks: group {} was retriggered", context.macro_id, sent, total, context.group_key);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+const DEFAULT_KEEP_AWAKE_INTERVAL_MS: u32 = 60_000;
+
+// Nudge the mouse every `amount` ms until `duration` ms have passed. Each nudge moves
+// 1px and straight back, so the cursor ends where it started even when stopped early.
+// Returns false if a newer session cancelled it.
+async fn keep_awake<R: Runtime>(
+    params: &ActionParams,
+    context: &ActionContext,
+    index: usize,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let total = std::time::Duration::from_millis(params.duration.unwrap_or(0) as u64);
+    let interval_ms = params.amount.map_or(DEFAULT_KEEP_AWAKE_INTERVAL_MS, |a| a.max(1) as u32);
+    let interval = std::time::Duration::from_millis(interval_ms as u64);
+    log::info!("Keeping {} awake for {:?}, nudging every {:?}", context.macro_id, total, interval);
+
+    let start = std::time::Instant::now();
+    let mut next_nudge = interval;
+    while next_nudge <= total {
+        if !sleep_unless_cancelled(next_nudge.saturating_sub(start.elapsed()), context).await {
+            log::debug!("Stopping KeepAwake of {} after {:?}: group {} was retriggered", context.macro_id, start.elapsed(), context.group_key);
+            return false;
+        }
+        if let Err(e) = execute_action_timed(ActionType::KeepAwake, params.clone(), context, app_handle).await {
+            report_action_error(app_handle, context, index, ActionType::KeepAwake, e);
+            break;
+        }
+        next_nudge += interval;
+    }
+
+    // Wait out the rest of the duration so following actions run when it ends
+    sleep_unless_cancelled(total.saturating_sub(start.elapsed()), context).await
+}
+
+const PIXEL_POLL_INTERVAL_MS: u64 = 100;
+const DEFAULT_PIXEL_WAIT_TIMEOUT_MS: u32 = 5000;
+
+// Parse "#RRGGBB" (the leading # is optional) into RGB components
+fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid color '{}': expected #RRGGBB", color);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn format_hex_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+// Read the color of the pixel at global screen coordinates (x, y). This captures the
+// whole monitor containing the point, which is why polling is kept to ~10 times a second.
+fn sample_pixel(x: i32, y: i32) -> Result<[u8; 3], String> {
+    let monitor = xcap::Monitor::from_point(x, y)
+        .map_err(|e| format!("No monitor at ({}, {}): {}", x, y, e))?;
+    let image = monitor.capture_image()
+        .map_err(|e| format!("Failed to capture screen: {}", e))?;
+    // The capture can be in physical pixels while monitor bounds are logical (HiDPI), so scale
+    let px = (x - monitor.x()) as i64 * image.width() as i64 / monitor.width().max(1) as i64;
+    let py = (y - monitor.y()) as i64 * image.height() as i64 / monitor.height().max(1) as i64;
+    let pixel = image.get_pixel_checked(px as u32, py as u32)
+        .ok_or_else(|| format!("Pixel ({}, {}) is outside the captured screen", x, y))?;
+    Ok([pixel.0[0], pixel.0[1], pixel.0[2]])
+}
+
+// Poll the pixel at x/y until every channel is within `amount` of the `text` color.
+// Returns Ok(false) if a newer session cancelled the wait and Err once `duration` ms pass.
+async fn wait_for_pixel(params: &ActionParams, context: &ActionContext) -> Result<bool, String> {
+    let x = params.x.ok_or("Missing x parameter for WaitForPixel")?;
+    let y = params.y.ok_or("Missing y parameter for WaitForPixel")?;
+    let target = parse_hex_color(params.text.as_deref().ok_or("Missing text (color) parameter for WaitForPixel")?)?;
+    let tolerance = params.amount.unwrap_or(0).clamp(0, 255) as u8;
+    let timeout_ms = params.duration.unwrap_or(DEFAULT_PIXEL_WAIT_TIMEOUT_MS);
+    let started_at = std::time::Instant::now();
+
+    log::debug!("Waiting for pixel ({}, {}) to become {} (±{})", x, y, format_hex_color(target), tolerance);
+    loop {
+        let color = tauri::async_runtime::spawn_blocking(move || sample_pixel(x, y))
+            .await
+            .map_err(|e| format!("Screen capture thread failed: {}", e))??;
+        if color.iter().zip(target).all(|(c, t)| c.abs_diff(t) <= tolerance) {
+            log::debug!("Pixel ({}, {}) matched after {}ms", x, y, started_at.elapsed().as_millis());
+            return Ok(true);
+        }
+        if started_at.elapsed() >= std::time::Duration::from_millis(timeout_ms as u64) {
+            return Err(format!(
+                "Pixel ({}, {}) did not become {} within {}ms (last seen {})",
+                x, y, format_hex_color(target), timeout_ms, format_hex_color(color)
+            ));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(PIXEL_POLL_INTERVAL_MS)).await;
+        if context.is_cancelled() {
+            log::debug!("Stopping WaitForPixel of {}: group {} was retriggered", context.macro_id, context.group_key);
+            return Ok(false);
+        }
+    }
+}
+
+// Flip a toggle macro's state, returning true if this press turns it on
+fn advance_toggle(macro_id: &str) -> bool {
+    let mut toggle_states = lock_or_recover(&APP_STATE.macro_toggle_states);
+    let state = toggle_states.entry(macro_id.to_string()).or_insert(false);
+    *state = !*state;
+    *state
+}
+
+// Pick the action list for this trigger: relative encoders use the increment/decrement
+// set and toggle macros alternate, both falling back to `actions` when their list is absent
+fn select_main_actions<'a>(macro_config: &'a MacroConfig, trigger: Option<&MidiData>) -> (&'static str, &'a [MacroAction]) {
+    let delta = trigger.and_then(|t| encoder_delta(macro_config, t)).unwrap_or(0);
+    if delta > 0 {
+        return ("increment", macro_config.increment_actions.as_deref().unwrap_or(&macro_config.actions));
+    }
+    if delta < 0 {
+        return ("decrement", macro_config.decrement_actions.as_deref().unwrap_or(&macro_config.actions));
+    }
+    if macro_config.toggle == Some(true) && !advance_toggle(&macro_config.id) {
+        return ("alternate", macro_config.alternate_actions.as_deref().unwrap_or(&macro_config.actions));
+    }
+    ("main", macro_config.actions.as_slice())
+}
+
+// Shortest time one pass of a repeating macro may take; shorter passes wait out the rest
+const MIN_REPEAT_PERIOD_MS: u64 = 10;
+
+async fn execute_main_actions<R: Runtime>(
+    macro_config: &MacroConfig,
+    trigger: Option<MidiData>,
+    session_id: u64,
+    app_handle: &AppHandle<R>,
+) -> bool {
+    let (phase, actions) = select_main_actions(macro_config, trigger.as_ref());
+
+    let mut context = ActionContext::new(macro_config, phase, Some(session_id));
+    context.trigger = trigger;
+    context.record_undo = true;
+    context.scroll_multiplier = scroll_acceleration(&context.group_key);
+    lock_or_recover(&APP_STATE.undo_stacks).insert(context.group_key.clone(), Vec::new());
+    let Some(repeat) = macro_config.repeat else {
+        let completed = run_actions(actions, &context, app_handle).await;
+        if !completed && context.is_cancelled() {
+            // A retrigger cut the run short, possibly between a press and its release
+            cleanup_mouse_state_for_macro(&macro_config.id);
+        }
+        return completed;
+    };
+    if actions.is_empty() {
+        return true;
+    }
+
+    let min_period = std::time::Duration::from_millis(MIN_REPEAT_PERIOD_MS);
+    let mut iteration: u32 = 0;
+    let mut iteration_started = std::time::Instant::now();
+    while repeat == 0 || iteration < repeat {
+        if iteration > 0 {
+            // Pace iterations so a loop without a Delay can't spin the CPU or flood the input queue
+            let rest = min_period.saturating_sub(iteration_started.elapsed());
+            if !rest.is_zero() && !sleep_unless_cancelled(rest, &context).await {
+                return false;
+            }
+            iteration_started = std::time::Instant::now();
+        }
+        if !run_actions(actions, &context, app_handle).await {
+            // Interrupted mid-iteration: don't leave keys or buttons from this pass held down
+            log::debug!("Repeat of {} interrupted after {} iterations", macro_config.id, iteration);
+            cleanup_mouse_state_for_macro(&macro_config.id);
+            return false;
+        }
+        iteration += 1;
+    }
+    true
+}
+
+async fn schedule_after_actions<R: Runtime>(
+    macro_config: MacroConfig,
+    app_handle: AppHandle<R>,
+    timeout_ms: u32,
+    session_id: u64,
+) {
+    let task_key = macro_config.groupId.as_ref()
+        .unwrap_or(&macro_config.id)
+        .clone();
+    
+    let has_after_actions = macro_config.after_actions
+        .as_ref()
+        .map_or(false, |a| !a.is_empty());
+    
+    let task_key_for_closure = task_key.clone();
+    let task_key_for_check = task_key.clone();
+    let abort_handle = tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_millis(timeout_ms as u64)).await;
+
+        let started_at = std::time::Instant::now();
+
+        // If a new session started, skip executing after-actions
+        if !is_current_session(&task_key_for_check, session_id) {
+            log::debug!("After-actions skipped due to newer session for group {}", task_key_for_check);
+            emit_macro_complete(&app_handle, "after-actions-complete", &macro_config, started_at, true);
+            return;
+        }
+        
+        let mut completed = true;
+        if has_after_actions {
+            if let Some(after_actions) = &macro_config.after_actions {
+                let context = ActionContext::new(&macro_config, "after", Some(session_id));
+                completed = run_actions(after_actions, &context, &app_handle).await;
+            }
+        }
+        
+        // Clean up
+        lock_or_recover(&APP_STATE.active_macros).remove(&task_key_for_closure);
+    lock_or_recover(&APP_STATE.before_action_states).remove(&task_key_for_closure);
+    lock_or_recover(&APP_STATE.before_notifiers).remove(&task_key_for_closure);
+        emit_macro_complete(&app_handle, "after-actions-complete", &macro_config, started_at, !completed);
+    }).abort_handle();
+    
+    // Store the task
+    lock_or_recover(&APP_STATE.active_macros).insert(
+        task_key,
+        ActiveMacro {
+            abort_handle,
+            last_triggered: std::time::Instant::now(),
+        }
+    );
+}
+
+fn message_type_name(message_type: &MidiMessageType) -> &'static str {
+    match message_type {
         MidiMessageType::NoteOff => "noteoff",
         MidiMessageType::NoteOn => "noteon",
         MidiMessageType::Aftertouch => "aftertouch",
@@ -1070,210 +4515,2164 @@ fn emit_midi_event<R: Runtime>(
         MidiMessageType::ProgramChange => "programchange",
         MidiMessageType::ChannelPressure => "channelpressure",
         MidiMessageType::PitchBend => "pitchbend",
+        MidiMessageType::SysEx => "sysex",
         MidiMessageType::Other => "other",
+    }
+}
+
+fn format_hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+// Window messages_per_second is averaged over
+const MIDI_RATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+struct MidiStats {
+    since: std::time::Instant,
+    total_messages: u64,
+    by_type: HashMap<&'static str, u64>,
+    // Arrival times within the last MIDI_RATE_WINDOW
+    recent: std::collections::VecDeque<std::time::Instant>,
+    macro_triggers: HashMap<String, u64>,
+}
+
+impl MidiStats {
+    fn new() -> Self {
+        Self {
+            since: std::time::Instant::now(),
+            total_messages: 0,
+            by_type: HashMap::new(),
+            recent: std::collections::VecDeque::new(),
+            macro_triggers: HashMap::new(),
+        }
+    }
+
+    fn prune(&mut self, now: std::time::Instant) {
+        while self.recent.front().is_some_and(|at| now.duration_since(*at) > MIDI_RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MidiStatsReport {
+    since_ms: u64, // How long the counters have been running
+    total_messages: u64,
+    by_type: HashMap<String, u64>, // Keyed like emit_message_types, e.g. "noteon"
+    messages_per_second: f64, // Over the last second
+    macro_triggers: HashMap<String, u64>, // MIDI-matched triggers by macro id
+}
+
+fn count_midi_message(midi_data: &MidiData) {
+    let now = std::time::Instant::now();
+    let mut stats = lock_or_recover(&APP_STATE.midi_stats);
+    stats.total_messages += 1;
+    *stats.by_type.entry(message_type_name(&midi_data.message_type)).or_insert(0) += 1;
+    stats.recent.push_back(now);
+    stats.prune(now);
+}
+
+fn count_macro_trigger(macro_id: &str) {
+    let mut stats = lock_or_recover(&APP_STATE.midi_stats);
+    *stats.macro_triggers.entry(macro_id.to_string()).or_insert(0) += 1;
+}
+
+// Command to read MIDI throughput and per-macro trigger counts since the last connect
+#[tauri::command]
+fn get_midi_stats() -> MidiStatsReport {
+    let now = std::time::Instant::now();
+    let mut stats = lock_or_recover(&APP_STATE.midi_stats);
+    stats.prune(now);
+    MidiStatsReport {
+        since_ms: now.duration_since(stats.since).as_millis() as u64,
+        total_messages: stats.total_messages,
+        by_type: stats.by_type.iter().map(|(name, count)| (name.to_string(), *count)).collect(),
+        messages_per_second: stats.recent.len() as f64 / MIDI_RATE_WINDOW.as_secs_f64(),
+        macro_triggers: stats.macro_triggers.clone(),
+    }
+}
+
+const MIDI_EMIT_WINDOW: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Whether a MIDI message should be sent to the frontend, per the emission filter and the
+// per-type rate limit. Only emission is filtered; every message is still matched against macros.
+fn should_emit_midi_event(midi_data: &MidiData) -> bool {
+    let type_name = message_type_name(&midi_data.message_type);
+    let limit = {
+        let settings = lock_or_recover(&APP_STATE.global_settings);
+        // System real-time messages (timing clock, start/stop, active sensing, reset)
+        if settings.suppress_realtime_events && midi_data.status >= 0xF8 {
+            return false;
+        }
+        if !settings.emit_message_types.is_empty() && !settings.emit_message_types.iter().any(|t| t == type_name) {
+            return false;
+        }
+        settings.max_midi_events_per_100ms
+    };
+    if limit == 0 {
+        return true;
+    }
+
+    let now = std::time::Instant::now();
+    let mut windows = lock_or_recover(&APP_STATE.midi_emit_windows);
+    let (window_start, count) = windows.entry(type_name).or_insert((now, 0));
+    if now.duration_since(*window_start) >= MIDI_EMIT_WINDOW {
+        *window_start = now;
+        *count = 0;
+    }
+    if *count >= limit {
+        return false;
+    }
+    *count += 1;
+    true
+}
+
+fn emit_midi_event<R: Runtime>(
+    midi_data: &MidiData,
+    timestamp: TimestampUs,
+    app_handle: &AppHandle<R>,
+) {
+    let type_name = message_type_name(&midi_data.message_type);
+    
+    let is_note = matches!(midi_data.message_type, MidiMessageType::NoteOn | MidiMessageType::NoteOff);
+    let is_cc = matches!(midi_data.message_type, MidiMessageType::ControlChange);
+    
+    let payload = RustMidiEvent {
+        status: midi_data.status,
+        data1: midi_data.data1,
+        data2: midi_data.data2,
+        timestamp,
+        type_name: type_name.to_string(),
+        channel: midi_data.channel,
+        note: if is_note { Some(midi_data.data1) } else { None },
+        velocity: if is_note { Some(midi_data.data2) } else { None },
+        controller: if is_cc { Some(midi_data.data1) } else { None },
+        value: if is_cc { Some(midi_data.data2) } else { None },
+        pitch_bend: midi_data.pitch_bend,
+        sysex_hex: midi_data.sysex.as_deref().map(format_hex_bytes),
+        nrpn_param: midi_data.nrpn.map(|(param, _)| param),
+        nrpn_value: midi_data.nrpn.map(|(_, value)| value),
+        raw_velocity: midi_data.raw_velocity,
+    };
+    
+    if let Err(e) = app_handle.emit("rust-midi-event", payload) {
+        log::error!("Failed to emit MIDI event: {}", e);
+    }
+}
+
+// Match a parsed (and assembled) MIDI message against the registered macros, spawn the
+// triggered ones and report the message to the frontend. Shared by the live input
+// callback and replay_recording.
+fn dispatch_midi_message<R: Runtime>(
+    midi_data: &MidiData,
+    timestamp: TimestampUs,
+    app_handle: &AppHandle<R>,
+) {
+    let macros_to_check = lock_or_recover(&APP_STATE.registered_macros).clone();
+    
+    let paused = *lock_or_recover(&APP_STATE.macros_paused);
+    
+    // Check for macro triggers
+    for macro_config in &macros_to_check {
+        if macro_config.enabled == Some(false) {
+            continue;
+        }
+        // Releases still go through while paused so nothing stays held
+        if is_momentary_release(macro_config, midi_data) {
+            release_momentary_macro(macro_config);
+            continue;
+        }
+        if paused {
+            continue;
+        }
+        if should_trigger_macro(macro_config, midi_data) {
+            log::info!("MIDI trigger matched for macro: {}", macro_config.name);
+            if is_debounced(macro_config) {
+                log::debug!("Debounced duplicate trigger for macro: {}", macro_config.name);
+                continue;
+            }
+            if macro_config.momentary == Some(true) {
+                lock_or_recover(&APP_STATE.held_momentary_macros).insert(macro_config.id.clone());
+            }
+            count_macro_trigger(&macro_config.id);
+            
+            spawn_macro_trigger(macro_config.clone(), Some(midi_data.clone()), app_handle.clone());
+        }
+    }
+    
+    // Emit the raw MIDI event unless filtered out or rate limited
+    if should_emit_midi_event(midi_data) {
+        emit_midi_event(midi_data, timestamp, app_handle);
+    }
+}
+
+// --- MIDI recording -------------------------------------------------------------
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedMidiMessage {
+    offset_ms: u64, // Time since the recording started
+    message: MidiData,
+}
+
+#[derive(Default)]
+struct MidiRecording {
+    started: Option<std::time::Instant>, // Set while recording
+    messages: Vec<RecordedMidiMessage>,
+}
+
+// Add an incoming message to the recording, if one is running
+fn record_midi_message(midi_data: &MidiData) {
+    let mut recording = lock_or_recover(&APP_STATE.midi_recording);
+    if let Some(started) = recording.started {
+        let offset_ms = started.elapsed().as_millis() as u64;
+        recording.messages.push(RecordedMidiMessage { offset_ms, message: midi_data.clone() });
+    }
+}
+
+// Command to start capturing incoming MIDI, discarding any previous recording
+#[tauri::command]
+fn start_midi_recording() {
+    let mut recording = lock_or_recover(&APP_STATE.midi_recording);
+    recording.messages.clear();
+    recording.started = Some(std::time::Instant::now());
+    log::info!("Started MIDI recording");
+}
+
+// Command to stop capturing; the recording is kept for save_recording. Returns its length.
+#[tauri::command]
+fn stop_midi_recording() -> usize {
+    let mut recording = lock_or_recover(&APP_STATE.midi_recording);
+    recording.started = None;
+    log::info!("Stopped MIDI recording with {} messages", recording.messages.len());
+    recording.messages.len()
+}
+
+// Command to write the current recording as JSON
+#[tauri::command]
+fn save_recording(path: String) -> Result<usize, String> {
+    let messages = lock_or_recover(&APP_STATE.midi_recording).messages.clone();
+    let json = serde_json::to_string_pretty(&messages)
+        .map_err(|e| format!("Failed to serialize recording: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    log::info!("Saved {} recorded MIDI messages to {}", messages.len(), path);
+    Ok(messages.len())
+}
+
+// Command to play a saved recording back through macro matching with its original timing,
+// as if the messages arrived from a device. Messages were recorded after 14-bit CC, NRPN
+// and pitch-bend assembly, so they skip those steps. Returns the number of messages queued;
+// playback continues in the background until it ends or stop_replay is called. Starting a
+// replay stops the one already playing.
+#[tauri::command]
+fn replay_recording<R: Runtime>(app_handle: AppHandle<R>, path: String) -> Result<usize, String> {
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let messages: Vec<RecordedMidiMessage> = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+    let count = messages.len();
+    log::info!("Replaying {} MIDI messages from {}", count, path);
+
+    let task = tauri::async_runtime::spawn(async move {
+        let started = tokio::time::Instant::now();
+        for recorded in messages {
+            tokio::time::sleep_until(started + std::time::Duration::from_millis(recorded.offset_ms)).await;
+            dispatch_midi_message(&recorded.message, midi_timestamp(), &app_handle);
+        }
+        log::info!("Finished replaying {} MIDI messages", count);
+    });
+    if let Some(previous) = lock_or_recover(&APP_STATE.replay_task).replace(task) {
+        previous.abort();
+    }
+    Ok(count)
+}
+
+// Command to stop a running replay_recording playback. Macros it already triggered keep running.
+#[tauri::command]
+fn stop_replay() {
+    if let Some(task) = lock_or_recover(&APP_STATE.replay_task).take() {
+        task.abort();
+        log::info!("Stopped MIDI replay");
+    }
+}
+
+// Live, injected and replayed messages are all stamped from this one clock, so their
+// timestamps compare. midir's own timestamps start at an arbitrary point per port.
+static MIDI_CLOCK_START: Lazy<std::time::Instant> = Lazy::new(std::time::Instant::now);
+
+fn midi_timestamp() -> TimestampUs {
+    MIDI_CLOCK_START.elapsed().as_micros() as TimestampUs
+}
+
+// Everything that happens to a raw message from an input port: parsing, assembly,
+// recording, MIDI learn and macro matching. Injected messages take the same path.
+fn handle_incoming_midi<R: Runtime>(message: &[u8], timestamp: TimestampUs, app_handle: &AppHandle<R>) {
+    // Early exit for invalid messages
+    let mut midi_data = match parse_midi_message(message) {
+        Some(data) => data,
+        None => return,
+    };
+    apply_velocity_curve(&mut midi_data);
+    assemble_14bit_cc(&mut midi_data);
+    assemble_nrpn(&mut midi_data);
+    track_pitch_bend(&mut midi_data);
+    track_pressure(&mut midi_data);
+
+    count_midi_message(&midi_data);
+    track_midi_clock(&midi_data);
+    record_midi_message(&midi_data);
+
+    // MIDI learn: hand this message to the waiting capture instead of matching macros
+    if let Some(sender) = lock_or_recover(&APP_STATE.midi_learn_sender).take() {
+        if sender.send(midi_data.clone()).is_ok() {
+            emit_midi_event(&midi_data, timestamp, app_handle);
+            return;
+        }
+    }
+
+    dispatch_midi_message(&midi_data, timestamp, app_handle);
+}
+
+// Command to feed raw MIDI bytes (e.g. [0x90, 36, 127]) through the same pipeline as a
+// connected input, for scripting triggers or exercising macros without hardware. It lets
+// any webview script fire macros, so it only exists with the midi-injection feature.
+#[cfg(any(test, feature = "midi-injection"))]
+#[tauri::command]
+fn inject_midi_message<R: Runtime>(app_handle: AppHandle<R>, bytes: Vec<u8>) -> Result<(), String> {
+    if parse_midi_message(&bytes).is_none() {
+        return Err(format!("Not a recognized MIDI message: {:02X?}", bytes));
+    }
+    handle_incoming_midi(&bytes, midi_timestamp(), &app_handle);
+    Ok(())
+}
+
+// An open input and the name its port had when it connected. Port indexes shift as devices
+// come and go, so the name tells whether an index still refers to this device.
+struct OpenMidiInput {
+    port_name: String,
+    _connection: MidiInputConnection<()>,
+}
+
+// Open a connection to the input port at port_index with the macro-matching callback
+fn connect_midi_port<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    port_index: usize,
+) -> Result<MidiInputConnection<()>, String> {
+    let midi_in = create_midi_input()?;
+    
+    let ports = midi_in.ports();
+    if port_index >= ports.len() {
+        return Err(format!("Port index {} out of range. Only {} ports available.", 
+                          port_index, ports.len()));
+    }
+    
+    let port = &ports[port_index];
+    let app_handle_clone = app_handle.clone();
+    
+    let connection = midi_in.connect(port, &midi_client_name(), move |_, message, _| {
+        handle_incoming_midi(message, midi_timestamp(), &app_handle_clone);
+    }, ())
+    .map_err(|e| create_midi_error("Failed to connect to MIDI device", e))?;
+    
+    Ok(connection)
+}
+
+// Replace your existing start_midi_listening_rust function with this:
+#[tauri::command]
+async fn start_midi_listening_rust<R: Runtime>(
+    app_handle: AppHandle<R>, 
+    port_index: usize
+) -> Result<(), String> {
+    let port_name = validate_and_get_port_name(port_index)?;
+    
+    // Reconnecting an open port would drop the MIDI events that arrive in between
+    let already_connected = lock_or_recover(&APP_STATE.midi_connections)
+        .get(&port_index)
+        .is_some_and(|open| open.port_name == port_name);
+    if already_connected {
+        emit_midi_status(&app_handle, format!("Already connected to MIDI device: {}", port_name));
+        return Ok(());
+    }
+    
+    // Other ports stay connected; only a previous connection to this port is replaced
+    cleanup_existing_connection(port_index)?;
+    let connection = connect_midi_port(&app_handle, port_index)?;
+    
+    // Store connection and notify frontend
+    let open = OpenMidiInput { port_name: port_name.clone(), _connection: connection };
+    lock_or_recover(&APP_STATE.midi_connections).insert(port_index, open);
+    *lock_or_recover(&APP_STATE.midi_stats) = MidiStats::new();
+    
+    if let Err(e) = app_handle.emit("midi-status", format!("Connected to MIDI device: {}", port_name)) {
+        log::error!("Failed to emit MIDI status: {}", e);
+    }
+    
+    remember_midi_port(&port_name);
+    spawn_midi_watchdog(app_handle, port_index, port_name);
+    Ok(())
+}
+
+// Settings live in the frontend and are pushed once it has loaded; if that doesn't happen
+// within this long, auto-connect goes by the settings the backend has
+const AUTO_CONNECT_SETTINGS_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+const MIDI_AUTO_CONNECT_EVENT: &str = "midi-auto-connect";
+
+// Keep the auto-connect outcome for get_auto_connect_status and tell a listening frontend
+fn report_auto_connect<R: Runtime>(app_handle: &AppHandle<R>, status: String) {
+    log::info!("{}", status);
+    *lock_or_recover(&APP_STATE.auto_connect_status) = Some(status.clone());
+    if let Err(e) = app_handle.emit(MIDI_AUTO_CONNECT_EVENT, status) {
+        log::error!("Failed to emit {}: {}", MIDI_AUTO_CONNECT_EVENT, e);
+    }
+}
+
+// Startup step: reconnect the last MIDI input that connected successfully, if it's present
+async fn auto_connect<R: Runtime>(app_handle: AppHandle<R>) {
+    let Some(port_name) = load_midi_port_memory().and_then(|memory| memory.last_port_name) else { return };
+    if tokio::time::timeout(AUTO_CONNECT_SETTINGS_WAIT, APP_STATE.settings_received.notified()).await.is_err() {
+        log::debug!("No settings from the frontend yet; auto-connecting with the current ones");
+    }
+    if !lock_or_recover(&APP_STATE.global_settings).auto_connect_last_port {
+        return;
+    }
+
+    let ports = match refresh_midi_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            report_auto_connect(&app_handle, format!("Auto-connect to {} failed: {}", port_name, e));
+            return;
+        }
+    };
+    let Some(port_index) = ports.iter().find(|port| port.name == port_name).map(|port| port.index) else {
+        report_auto_connect(&app_handle, format!("Auto-connect: last MIDI device {} not found", port_name));
+        return;
+    };
+
+    match start_midi_listening_rust(app_handle.clone(), port_index).await {
+        Ok(()) => report_auto_connect(&app_handle, format!("Auto-connected to MIDI device: {}", port_name)),
+        Err(e) => report_auto_connect(&app_handle, format!("Auto-connect to {} failed: {}", port_name, e)),
+    }
+}
+
+// Command to read how the startup auto-connect went; None while it hasn't run (or had no port)
+#[tauri::command]
+fn get_auto_connect_status() -> Option<String> {
+    lock_or_recover(&APP_STATE.auto_connect_status).clone()
+}
+
+// --- Reconnection watchdog -------------------------------------------------------
+// midir doesn't report unplugged devices, so the watchdog polls the port list and
+// reconnects by name. Each start registers a fresh generation for its port and each
+// stop retires it, so stale watchdogs exit and an intentional stop is never undone.
+fn register_watchdog(port_index: usize) -> u64 {
+    let mut generation = lock_or_recover(&APP_STATE.midi_watchdog_generation);
+    *generation = generation.wrapping_add(1);
+    lock_or_recover(&APP_STATE.midi_watchdogs).insert(port_index, *generation);
+    *generation
+}
+
+fn retire_watchdog(port_index: usize) {
+    lock_or_recover(&APP_STATE.midi_watchdogs).remove(&port_index);
+}
+
+fn is_current_watchdog(port_index: usize, generation: u64) -> bool {
+    lock_or_recover(&APP_STATE.midi_watchdogs).get(&port_index) == Some(&generation)
+}
+
+fn find_port_index_by_name(port_name: &str) -> Option<usize> {
+    let midi_in = create_midi_input().ok()?;
+    let ports = midi_in.ports();
+    ports
+        .iter()
+        .position(|port| midi_in.port_name(port).is_ok_and(|name| name == port_name))
+}
+
+fn emit_midi_status<R: Runtime>(app_handle: &AppHandle<R>, status: String) {
+    log::info!("{}", status);
+    if let Err(e) = app_handle.emit("midi-status", status) {
+        log::error!("Failed to emit MIDI status: {}", e);
+    }
+}
+
+// Floor for reconnect_interval_ms so a 0 setting can't turn the watchdog into a busy loop
+const MIN_RECONNECT_INTERVAL_MS: u64 = 100;
+
+fn spawn_midi_watchdog<R: Runtime>(app_handle: AppHandle<R>, port_index: usize, port_name: String) {
+    let generation = register_watchdog(port_index);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = lock_or_recover(&APP_STATE.global_settings).reconnect_interval_ms
+                .max(MIN_RECONNECT_INTERVAL_MS);
+            tokio::time::sleep(std::time::Duration::from_millis(interval)).await;
+            if !is_current_watchdog(port_index, generation) {
+                return;
+            }
+            if find_port_index_by_name(&port_name).is_some() {
+                continue;
+            }
+
+            // The device is gone: drop the dead connection and start retrying
+            lock_or_recover(&APP_STATE.midi_connections).remove(&port_index);
+            emit_midi_status(&app_handle, format!("MIDI device disconnected: {}", port_name));
+
+            let mut attempts: u32 = 0;
+            loop {
+                let (interval, max_attempts) = {
+                    let settings = lock_or_recover(&APP_STATE.global_settings);
+                    (settings.reconnect_interval_ms.max(MIN_RECONNECT_INTERVAL_MS), settings.reconnect_max_attempts)
+                };
+                if max_attempts > 0 && attempts >= max_attempts {
+                    emit_midi_status(&app_handle, format!("Gave up reconnecting to MIDI device {} after {} attempts", port_name, attempts));
+                    return;
+                }
+                attempts += 1;
+
+                tokio::time::sleep(std::time::Duration::from_millis(interval)).await;
+                if !is_current_watchdog(port_index, generation) {
+                    return;
+                }
+
+                let reconnected = find_port_index_by_name(&port_name)
+                    .ok_or_else(|| "port not found".to_string())
+                    .and_then(|current_index| connect_midi_port(&app_handle, current_index));
+                match reconnected {
+                    Ok(connection) => {
+                        // Re-check under the connections lock: a stop that retired this watchdog
+                        // while we were connecting must not see the port come back
+                        let mut connections = lock_or_recover(&APP_STATE.midi_connections);
+                        if !is_current_watchdog(port_index, generation) {
+                            return;
+                        }
+                        // Keep it under the original key so stop_midi_port still finds it
+                        connections.insert(port_index, OpenMidiInput { port_name: port_name.clone(), _connection: connection });
+                        drop(connections);
+                        *lock_or_recover(&APP_STATE.midi_stats) = MidiStats::new();
+                        emit_midi_status(&app_handle, format!("MIDI device reconnected: {}", port_name));
+                        break;
+                    },
+                    Err(e) => {
+                        log::warn!("Reconnect attempt {} to {} failed: {}", attempts, port_name, e);
+                    },
+                }
+            }
+        }
+    });
+}
+
+// --- Scheduled triggers -----------------------------------------------------------
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_millis(250);
+const MIN_SCHEDULE_INTERVAL_MS: u64 = 100;
+
+// When each scheduled macro last fired; kept by the scheduler task itself
+#[derive(Debug, Default)]
+struct ScheduleTracker {
+    intervals: HashMap<MacroId, std::time::Instant>,
+    daily: HashMap<MacroId, chrono::NaiveDate>,
+}
+
+impl ScheduleTracker {
+    // Whether the macro's schedule is due now, recording the run if so
+    fn is_due(&mut self, macro_id: &str, schedule: ScheduleSpec) -> bool {
+        match schedule {
+            ScheduleSpec::Interval { ms } => {
+                let now = std::time::Instant::now();
+                let last_run = *self.intervals.entry(macro_id.to_string()).or_insert(now);
+                if now.duration_since(last_run) < std::time::Duration::from_millis(ms) {
+                    return false;
+                }
+                self.intervals.insert(macro_id.to_string(), now);
+                true
+            },
+            ScheduleSpec::Daily { hour, minute } => {
+                use chrono::Timelike;
+                let now = chrono::Local::now();
+                let today = now.date_naive();
+                if now.hour() != hour as u32 || now.minute() != minute as u32 || self.daily.get(macro_id) == Some(&today) {
+                    return false;
+                }
+                self.daily.insert(macro_id.to_string(), today);
+                true
+            },
+        }
+    }
+}
+
+// Background task that fires macros with a schedule through the normal trigger flow
+fn spawn_scheduler<R: Runtime>(app_handle: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut tracker = ScheduleTracker::default();
+        loop {
+            tokio::time::sleep(SCHEDULER_TICK).await;
+            if *lock_or_recover(&APP_STATE.schedules_paused) {
+                continue;
+            }
+
+            let scheduled: Vec<MacroConfig> = lock_or_recover(&APP_STATE.registered_macros)
+                .iter()
+                .filter(|m| m.schedule.is_some() && m.enabled != Some(false))
+                .cloned()
+                .collect();
+            // Forget macros that were removed or lost their schedule
+            tracker.intervals.retain(|id, _| scheduled.iter().any(|m| &m.id == id));
+            tracker.daily.retain(|id, _| scheduled.iter().any(|m| &m.id == id));
+
+            for macro_config in scheduled {
+                let Some(schedule) = macro_config.schedule else { continue };
+                if !tracker.is_due(&macro_config.id, schedule) {
+                    continue;
+                }
+                log::info!("Schedule triggered macro: {}", macro_config.name);
+                spawn_macro_trigger(macro_config, None, app_handle.clone());
+            }
+        }
+    });
+}
+
+// Command to stop scheduled macros from firing until resume_schedules
+#[tauri::command]
+fn pause_schedules() -> Result<(), String> {
+    *lock_or_recover(&APP_STATE.schedules_paused) = true;
+    log::info!("Scheduled macros paused");
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_schedules() -> Result<(), String> {
+    *lock_or_recover(&APP_STATE.schedules_paused) = false;
+    log::info!("Scheduled macros resumed");
+    Ok(())
+}
+
+const MACROS_PAUSED_EVENT: &str = "macros-paused";
+const MACROS_RESUMED_EVENT: &str = "macros-resumed";
+
+// Command to stop MIDI from triggering macros without disconnecting; the MIDI monitor
+// keeps receiving events and schedules are unaffected
+#[tauri::command]
+fn pause_macros<R: Runtime>(app_handle: AppHandle<R>) -> Result<(), String> {
+    set_macros_paused(true, MACROS_PAUSED_EVENT, &app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_macros<R: Runtime>(app_handle: AppHandle<R>) -> Result<(), String> {
+    set_macros_paused(false, MACROS_RESUMED_EVENT, &app_handle);
+    Ok(())
+}
+
+fn set_macros_paused<R: Runtime>(paused: bool, event: &str, app_handle: &AppHandle<R>) {
+    *lock_or_recover(&APP_STATE.macros_paused) = paused;
+    log::info!("MIDI macro triggers {}", if paused { "paused" } else { "resumed" });
+    if let Err(e) = app_handle.emit(event, ()) {
+        log::error!("Failed to emit {}: {}", event, e);
+    }
+}
+
+const DEFAULT_MIDI_LEARN_TIMEOUT_SECS: u64 = 10;
+
+// Message captured by midi_learn, in the same terms the frontend uses for triggers
+#[derive(Debug, Clone, Serialize)]
+pub struct MidiLearnResult {
+    pub type_name: String,
+    pub channel: u8,
+    pub data1: u8,
+    pub data2: u8,
+}
+
+// Capture the next incoming MIDI message so the UI can fill in note/channel/value.
+// Macro triggering is suspended only for that one message.
+#[tauri::command]
+async fn midi_learn<R: Runtime>(
+    app_handle: AppHandle<R>,
+    timeout_secs: Option<u64>,
+) -> Result<MidiLearnResult, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    // A newer learn request replaces (and thereby cancels) any capture still waiting
+    lock_or_recover(&APP_STATE.midi_learn_sender).replace(tx);
+
+    let timeout_secs = timeout_secs.unwrap_or(DEFAULT_MIDI_LEARN_TIMEOUT_SECS);
+    match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), rx).await {
+        Ok(Ok(midi_data)) => {
+            let result = MidiLearnResult {
+                type_name: message_type_name(&midi_data.message_type).to_string(),
+                channel: midi_data.channel,
+                data1: midi_data.data1,
+                data2: midi_data.data2,
+            };
+            log::info!("MIDI learn captured: {:?}", result);
+            let _ = app_handle.emit("midi-learn-result", result.clone());
+            Ok(result)
+        },
+        Ok(Err(_)) => {
+            let _ = app_handle.emit("midi-learn-cancelled", "replaced");
+            Err("MIDI learn was cancelled by a newer request".to_string())
+        },
+        Err(_) => {
+            // Our receiver is gone, so only clear the slot if it still holds our (now closed) sender
+            {
+                let mut sender = lock_or_recover(&APP_STATE.midi_learn_sender);
+                if sender.as_ref().is_some_and(|s| s.is_closed()) {
+                    *sender = None;
+                }
+            }
+            let _ = app_handle.emit("midi-learn-cancelled", "timeout");
+            Err(format!("MIDI learn timed out after {}s", timeout_secs))
+        },
+    }
+}
+
+// Close every open MIDI input
+#[tauri::command]
+fn stop_midi_listening_rust<R: Runtime>(app_handle: AppHandle<R>) -> Result<(), String> {
+    // Retire the watchdogs so they don't reconnect after an intentional stop
+    lock_or_recover(&APP_STATE.midi_watchdogs).clear();
+    let mut connections = lock_or_recover(&APP_STATE.midi_connections);
+    if !connections.is_empty() {
+        connections.clear();
+        if let Err(e) = app_handle.emit("midi-status", "MIDI connection closed") {
+            log::error!("Failed to emit MIDI status: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// Close a single MIDI input, leaving the other ports connected
+#[tauri::command]
+fn stop_midi_port<R: Runtime>(app_handle: AppHandle<R>, port_index: usize) -> Result<(), String> {
+    retire_watchdog(port_index);
+    if lock_or_recover(&APP_STATE.midi_connections).remove(&port_index).is_some() {
+        if let Err(e) = app_handle.emit("midi-status", format!("MIDI connection closed on port {}", port_index)) {
+            log::error!("Failed to emit MIDI status: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// MIDI Event coming from Rust
+#[derive(Debug, Clone, Serialize)]
+pub struct RustMidiEvent {
+    pub status: u8,
+    pub data1: u8,
+    pub data2: u8,
+    pub timestamp: u64, // Microseconds since the app's MIDI clock started
+    pub type_name: String, // "noteon", "noteoff", "controlchange", etc.
+    pub channel: u8,       // 1-16, or 0 for system messages (SysEx, clock, ...)
+    pub note: Option<u8>,  // For noteon/noteoff
+    pub velocity: Option<u8>, // For noteon/noteoff
+    pub controller: Option<u8>, // For controlchange
+    pub value: Option<u8>,     // For controlchange
+    pub pitch_bend: Option<i16>, // For pitchbend, signed -8192..8191
+    pub sysex_hex: Option<String>, // For sysex, e.g. "F0 7E 7F 06 01 F7"
+    pub nrpn_param: Option<u16>, // For the CC that completes an NRPN, 0-16383
+    pub nrpn_value: Option<u16>, // 0-16383
+    pub raw_velocity: Option<u8>, // For noteon, the velocity before the velocity curve when it differs
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MonitorInfo {
+    index: u32,
+    name: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+}
+
+// Command to list the connected monitors; `index` is what ActionParams.monitor refers to
+#[tauri::command]
+fn list_monitors<R: Runtime>(app_handle: AppHandle<R>) -> Result<Vec<MonitorInfo>, String> {
+    let monitors = app_handle
+        .available_monitors()
+        .map_err(|e| format!("Failed to enumerate monitors: {}", e))?;
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| MonitorInfo {
+            index: index as u32,
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+        })
+        .collect())
+}
+
+// Translate window-relative mouse coordinates into global ones, looking the window up
+// now so the macro follows it when it is moved. Takes precedence over `monitor`.
+async fn resolve_window_coordinates(action_type: &ActionType, mut params: ActionParams) -> Result<ActionParams, String> {
+    let Some(title) = params.window_title.clone() else {
+        return Ok(params);
     };
-    
-    let is_note = matches!(midi_data.message_type, MidiMessageType::NoteOn | MidiMessageType::NoteOff);
-    let is_cc = matches!(midi_data.message_type, MidiMessageType::ControlChange);
-    
-    let payload = RustMidiEvent {
-        status: midi_data.status,
-        data1: midi_data.data1,
-        data2: midi_data.data2,
-        timestamp,
-        type_name: type_name.to_string(),
-        channel: midi_data.channel,
-        note: if is_note { Some(midi_data.data1) } else { None },
-        velocity: if is_note { Some(midi_data.data2) } else { None },
-        controller: if is_cc { Some(midi_data.data1) } else { None },
-        value: if is_cc { Some(midi_data.data2) } else { None },
+    let moves_to_points = matches!(
+        action_type,
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick | ActionType::DragTo
+    );
+    if !moves_to_points || params.relative == Some(true) {
+        return Ok(params);
+    }
+
+    // The lookup runs wmctrl/osascript or walks every window, so keep it off the async runtime
+    let lookup = title.clone();
+    let origin = tauri::async_runtime::spawn_blocking(move || window_origin(&lookup))
+        .await
+        .map_err(|e| format!("Window lookup thread failed: {}", e))??;
+    log::debug!("Window '{}' is at {:?}", title, origin);
+    params.x = params.x.map(|x| x + origin.0);
+    params.y = params.y.map(|y| y + origin.1);
+    params.end_x = params.end_x.map(|x| x + origin.0);
+    params.end_y = params.end_y.map(|y| y + origin.1);
+    params.monitor = None;
+    Ok(params)
+}
+
+// Translate monitor-relative MouseMove coordinates into global ones.
+// Relative moves and actions without a monitor are returned unchanged.
+fn resolve_monitor_coordinates<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    action_type: &ActionType,
+    mut params: ActionParams,
+) -> Result<ActionParams, String> {
+    let Some(index) = params.monitor else {
+        return Ok(params);
     };
-    
-    if let Err(e) = app_handle.emit("rust-midi-event", payload) {
-        eprintln!("Failed to emit MIDI event: {}", e);
+    let moves_to_points = matches!(
+        action_type,
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick | ActionType::DragTo
+    );
+    if !moves_to_points || params.relative == Some(true) {
+        return Ok(params);
     }
+
+    let origin = monitor_origin(app_handle, index)?;
+    params.x = params.x.map(|x| x + origin.0);
+    params.y = params.y.map(|y| y + origin.1);
+    params.end_x = params.end_x.map(|x| x + origin.0);
+    params.end_y = params.end_y.map(|y| y + origin.1);
+    Ok(params)
 }
 
-// Replace your existing start_midi_listening_rust function with this:
-#[tauri::command]
-async fn start_midi_listening_rust<R: Runtime>(
-    app_handle: AppHandle<R>, 
-    port_index: usize
+// Top-left corner of a monitor in the global coordinates enigo moves the mouse in
+fn monitor_origin<R: Runtime>(app_handle: &AppHandle<R>, index: u32) -> Result<(i32, i32), String> {
+    let monitors = app_handle
+        .available_monitors()
+        .map_err(|e| format!("Failed to enumerate monitors: {}", e))?;
+    let monitor = monitors
+        .get(index as usize)
+        .ok_or_else(|| format!("Monitor {} not found ({} connected)", index, monitors.len()))?;
+
+    // Tauri reports physical pixels; enigo works in points on macOS
+    let position = monitor.position();
+    #[cfg(target_os = "macos")]
+    let origin = (
+        (position.x as f64 / monitor.scale_factor()).round() as i32,
+        (position.y as f64 / monitor.scale_factor()).round() as i32,
+    );
+    #[cfg(not(target_os = "macos"))]
+    let origin = (position.x, position.y);
+    Ok(origin)
+}
+
+const BLOCKED_MOVE_EVENT: &str = "blocked-move";
+
+#[derive(Debug, Clone, Serialize)]
+struct BlockedMoveEvent {
+    action_type: ActionType,
+    x: i32, // Global point the move would have landed on or the button pressed at
+    y: i32,
+    region: ForbiddenRegion,
+}
+
+// Refuse mouse actions (already resolved to global coordinates) that would move the cursor
+// to, or press a button at, a point inside one of the forbidden_regions. Relative targets
+// and clicks at the cursor are worked out from the cursor position as the owner's input
+// thread sees it, i.e. after the moves the macro already queued.
+async fn check_forbidden_regions<R: Runtime>(
+    app_handle: Option<&AppHandle<R>>,
+    action_type: &ActionType,
+    params: &ActionParams,
+    owner: Option<&str>,
 ) -> Result<(), String> {
-    cleanup_existing_connection()?;
-    let port_name = validate_and_get_port_name(port_index)?;
-    let midi_in = create_midi_input()?;
-    
-    let ports = midi_in.ports();
-    if port_index >= ports.len() {
-        return Err(format!("Port index {} out of range. Only {} ports available.", 
-                          port_index, ports.len()));
+    let moves_mouse = match action_type {
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick
+        | ActionType::DragTo | ActionType::MouseDrag => true,
+        ActionType::MouseClick => !params.button.as_deref().is_some_and(is_scroll_button),
+        _ => false,
+    };
+    if !moves_mouse {
+        return Ok(());
     }
-    
-    let port = &ports[port_index];
-    let app_handle_clone = app_handle.clone();
-    
-    let connection = midi_in.connect(port, "midi-connection", move |timestamp, message, _| {
-        // Early exit for invalid messages
-        let midi_data = match parse_midi_message(message) {
-            Some(data) => data,
-            None => return,
-        };
-        
-       
-        
-        let app_handle_for_macros = app_handle_clone.clone();
-        
-        // Get macros and settings in a single lock acquisition
-        let (macros_to_check, _settings) = {
-            let registered_macros = APP_STATE.registered_macros.lock().unwrap();
-            let settings = APP_STATE.global_settings.lock().unwrap();
-            (registered_macros.clone(), settings.clone())
+    let regions = lock_or_recover(&APP_STATE.global_settings).forbidden_regions.clone();
+    if regions.is_empty() {
+        return Ok(());
+    }
+
+    let relative = params.relative == Some(true);
+    let needs_cursor = match action_type {
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick => relative,
+        ActionType::DragTo => params.x.is_none() || params.y.is_none(),
+        _ => true,
+    };
+    let cursor = if needs_cursor {
+        query_input(owner, |enigo| enigo.mouse_location()).await?
+    } else {
+        (0, 0)
+    };
+    let mut targets = Vec::new();
+    match action_type {
+        ActionType::MouseMove | ActionType::MouseMoveSmooth | ActionType::MoveAndClick => {
+            if let (Some(x), Some(y)) = (params.x, params.y) {
+                targets.push(if relative { (cursor.0 + x, cursor.1 + y) } else { (x, y) });
+            }
+        },
+        ActionType::DragTo => {
+            // The button goes down at the start point and comes up at the end point
+            targets.push(match (params.x, params.y) {
+                (Some(x), Some(y)) => (x, y),
+                _ => cursor,
+            });
+            if let (Some(x), Some(y)) = (params.end_x, params.end_y) {
+                targets.push((x, y));
+            }
+        },
+        ActionType::MouseDrag => {
+            targets.push(cursor);
+            if let (Some(dx), Some(dy)) = (params.x, params.y) {
+                targets.push((cursor.0 + dx, cursor.1 + dy));
+            }
+        },
+        // Clicks land wherever the cursor is
+        _ => targets.push(cursor),
+    }
+
+    for region in regions {
+        let origin = match region.monitor {
+            Some(index) => {
+                let app = app_handle.ok_or_else(|| format!("Can't place forbidden region on monitor {} without the app", index))?;
+                monitor_origin(app, index)?
+            },
+            None => (0, 0),
         };
-        
-        // Check for macro triggers
-        for macro_config in &macros_to_check {
-            if should_trigger_macro(macro_config, &midi_data) {
-                midi_log!("MIDI trigger matched for macro: {}", macro_config.name);
-                
-                let macro_clone = macro_config.clone();
-                let app_handle = app_handle_for_macros.clone();
-                
-                // Spawn async task to handle the trigger
-                let _ = tauri::async_runtime::spawn(async move {
-                    handle_macro_trigger(macro_clone, app_handle).await;
-                });
+        let left = region.x + origin.0;
+        let top = region.y + origin.1;
+        let inside = |&(x, y): &(i32, i32)| (left..left + region.width).contains(&x) && (top..top + region.height).contains(&y);
+        if let Some(&(x, y)) = targets.iter().find(|target| inside(target)) {
+            let name = region.label.clone().unwrap_or_else(|| format!("{}x{} at ({}, {})", region.width, region.height, left, top));
+            log::warn!("Blocked {:?} at ({}, {}): inside forbidden region {}", action_type, x, y, name);
+            if let Some(app) = app_handle {
+                let event = BlockedMoveEvent { action_type: action_type.clone(), x, y, region };
+                if let Err(e) = app.emit(BLOCKED_MOVE_EVENT, event) {
+                    log::error!("Failed to emit {}: {}", BLOCKED_MOVE_EVENT, e);
+                }
             }
+            return Err(format!("{:?} at ({}, {}) blocked: inside forbidden region {}", action_type, x, y, name));
+        }
+    }
+    Ok(())
+}
+
+// Command to get cursor position
+#[tauri::command]
+async fn get_cursor_position() -> Result<(i32, i32), String> {
+    // Get the mouse position and explicitly create a tuple in (x, y) order
+    // This ensures the coordinates are in the expected order
+    let position = query_input(None, |enigo| enigo.mouse_location()).await?;
+    
+    // Log coordinates for debugging
+    log::debug!("Cursor position: x={}, y={}", position.0, position.1);
+    
+    // Return explicitly as (x, y)
+    Ok((position.0, position.1))
+}
+
+// Switch the active log level, rejecting names `log` doesn't know so they never reach the settings
+fn apply_log_level(level: &str) -> Result<(), String> {
+    let filter = level.parse::<log::LevelFilter>()
+        .map_err(|_| format!("Unknown log level '{}' (expected off, error, warn, info, debug or trace)", level))?;
+    log::set_max_level(filter);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AppInfo {
+    version: String,
+    tauri_version: String,
+    os: String,
+    arch: String,
+    debug: bool,
+}
+
+// Command to report which build is running, for bug reports
+#[tauri::command]
+fn get_app_info() -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        debug: cfg!(debug_assertions),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SelfTestCheck {
+    name: String,
+    passed: bool,
+    detail: Option<String>, // What went wrong, or extra context when passed
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SelfTestReport {
+    os: String,
+    passed: bool, // Every check passed
+    checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestCheck {
+    fn new(name: &str, result: Result<Option<String>, String>) -> Self {
+        match result {
+            Ok(detail) => SelfTestCheck { name: name.to_string(), passed: true, detail },
+            Err(e) => SelfTestCheck { name: name.to_string(), passed: false, detail: Some(e) },
+        }
+    }
+}
+
+// Whether the OS lets this process synthesize input; None where there is no such permission
+#[cfg(target_os = "macos")]
+fn input_permission_granted() -> Option<bool> {
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+    Some(unsafe { AXIsProcessTrusted() })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn input_permission_granted() -> Option<bool> {
+    None
+}
+
+// Run an Enigo probe, turning a panic (e.g. no display server to connect to) into an error
+fn probe_input<T>(probe: impl FnOnce() -> T + std::panic::UnwindSafe) -> Result<T, String> {
+    std::panic::catch_unwind(probe).map_err(|panic| {
+        panic.downcast_ref::<String>().cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "panicked".to_string())
+    })
+}
+
+// Command to check that input simulation and MIDI work, for diagnosing "nothing happens"
+// reports. It only reads the cursor and moves it by 0,0, so it's safe to run any time.
+#[tauri::command]
+fn run_self_test() -> SelfTestReport {
+    let mut checks = Vec::new();
+
+    match input_permission_granted() {
+        Some(true) => checks.push(SelfTestCheck::new("accessibility", Ok(None))),
+        Some(false) => checks.push(SelfTestCheck::new("accessibility", Err(
+            "Accessibility permission is not granted; allow openGRADER in System Settings > Privacy & Security > Accessibility and restart it".to_string()
+        ))),
+        None => {},
+    }
+
+    let enigo = probe_input(create_enigo);
+    checks.push(SelfTestCheck::new("input_init", enigo.as_ref().map(|_| None).map_err(|e| format!("Failed to initialize input simulation: {}", e))));
+    if let Ok(mut enigo) = enigo {
+        let location = probe_input(std::panic::AssertUnwindSafe(|| enigo.mouse_location()));
+        checks.push(SelfTestCheck::new("mouse_read", location.map(|(x, y)| Some(format!("cursor at {}, {}", x, y)))));
+        let moved = probe_input(std::panic::AssertUnwindSafe(|| enigo.mouse_move_relative(0, 0)));
+        checks.push(SelfTestCheck::new("mouse_move", moved.map(|_| None)));
+    }
+
+    let midi = MidiInput::new(&midi_client_name())
+        .map(|midi_in| Some(format!("{} input ports", midi_in.port_count())))
+        .map_err(|e| format!("Failed to initialize MIDI: {}", e));
+    checks.push(SelfTestCheck::new("midi_input", midi));
+
+    let passed = checks.iter().all(|c| c.passed);
+    if passed {
+        log::info!("Self-test passed");
+    } else {
+        log::warn!("Self-test failed: {:?}", checks.iter().filter(|c| !c.passed).collect::<Vec<_>>());
+    }
+    SelfTestReport { os: std::env::consts::OS.to_string(), passed, checks }
+}
+
+// Command to get global settings
+#[tauri::command]
+fn get_global_settings() -> Result<GlobalSettings, String> {
+    let settings = lock_or_recover(&APP_STATE.global_settings);
+    Ok(settings.clone())
+}
+
+// GlobalSettings as a JSON object keyed by field name
+fn settings_fields(settings: &GlobalSettings) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match serde_json::to_value(settings) {
+        Ok(serde_json::Value::Object(fields)) => Ok(fields),
+        Ok(_) => Err("Global settings did not serialize to an object".to_string()),
+        Err(e) => Err(format!("Failed to serialize global settings: {}", e)),
+    }
+}
+
+// Command to read a single global setting by field name, e.g. "dry_run"
+#[tauri::command]
+fn get_setting(key: String) -> Result<serde_json::Value, String> {
+    let mut fields = settings_fields(&lock_or_recover(&APP_STATE.global_settings))?;
+    fields.remove(&key).ok_or_else(|| format!("Unknown setting '{}'", key))
+}
+
+// Command to change a single global setting under the lock. Unlike update_global_settings
+// it doesn't overwrite fields another settings panel changed in the meantime.
+// Returns the settings after the change.
+#[tauri::command]
+fn set_setting(key: String, value: serde_json::Value) -> Result<GlobalSettings, String> {
+    let mut settings = lock_or_recover(&APP_STATE.global_settings);
+    let mut fields = settings_fields(&settings)?;
+    let field = fields.get_mut(&key).ok_or_else(|| format!("Unknown setting '{}'", key))?;
+    *field = value;
+    let updated: GlobalSettings = serde_json::from_value(serde_json::Value::Object(fields))
+        .map_err(|e| format!("Invalid value for setting '{}': {}", key, e))?;
+
+    if key == "log_level" {
+        apply_log_level(&updated.log_level)?;
+    }
+    *settings = updated;
+    log::info!("Global setting {} updated: {:?}", key, *settings);
+    let updated = settings.clone();
+    drop(settings);
+    APP_STATE.settings_received.notify_one();
+    Ok(updated)
+}
+
+// Command to update global settings
+#[tauri::command]
+fn update_global_settings(new_settings: GlobalSettings) -> Result<(), String> {
+    let mut settings = lock_or_recover(&APP_STATE.global_settings);
+    apply_log_level(&new_settings.log_level)?;
+    *settings = new_settings;
+    log::info!("Global settings updated: {:?}", *settings);
+    drop(settings);
+    APP_STATE.settings_received.notify_one();
+    Ok(())
+}
+#[derive(Debug, Clone, Serialize)]
+struct HeldInput {
+    name: String,
+    macro_id: Option<String>, // Macro that pressed it, if it came from a macro
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct InputState {
+    keys: Vec<HeldInput>,
+    mouse_buttons: Vec<HeldInput>,
+}
+
+// Command to report which keys and mouse buttons the app is currently holding down
+#[tauri::command]
+fn get_input_state() -> Result<InputState, String> {
+    let keys = lock_or_recover(&APP_STATE.key_state)
+        .iter()
+        .map(|(key, owner)| HeldInput { name: key_to_string(*key), macro_id: owner.clone() })
+        .collect();
+    let mouse_buttons = lock_or_recover(&APP_STATE.mouse_state)
+        .iter()
+        .map(|(button, owner)| HeldInput { name: mouse_button_to_string(*button), macro_id: owner.clone() })
+        .collect();
+    Ok(InputState { keys, mouse_buttons })
+}
+
+// Event the frontend can emit (e.g. from a global hotkey) to trigger emergency_release
+pub const EMERGENCY_RELEASE_EVENT: &str = "emergency-release";
+
+// Stop every pending group and release every tracked key and button, whichever macro holds it
+fn release_all_inputs() {
+    let aborted = {
+        let mut active_macros = lock_or_recover(&APP_STATE.active_macros);
+        for active_macro in active_macros.values() {
+            active_macro.abort_handle.abort();
+        }
+        active_macros.drain().count()
+    };
+    lock_or_recover(&APP_STATE.before_action_states).clear();
+    lock_or_recover(&APP_STATE.held_momentary_macros).clear();
+    lock_or_recover(&APP_STATE.latched_modifiers).clear();
+
+    // Advance every group's session so in-flight before/main actions stop at their next step,
+    // then wake anything waiting on a before_actions notifier so it notices and bails out
+    {
+        let mut sessions = lock_or_recover(&APP_STATE.group_sessions);
+        for session in sessions.values_mut() {
+            *session = session.saturating_add(1);
         }
-        
-        // Always emit the raw MIDI event
-        emit_midi_event(&midi_data, timestamp, &app_handle_for_macros);
-        
-    }, ())
-    .map_err(|e| create_midi_error("Failed to connect to MIDI device", e))?;
-    
-    // Store connection and notify frontend
-    APP_STATE.midi_connection.lock().unwrap().replace(connection);
-    
-    if let Err(e) = app_handle.emit("midi-status", format!("Connected to MIDI device: {}", port_name)) {
-        eprintln!("Failed to emit MIDI status: {}", e);
     }
-    
+    for (_, notifier) in lock_or_recover(&APP_STATE.before_notifiers).drain() {
+        notifier.notify_waiters();
+    }
+
+    let buttons: Vec<(MouseButton, Option<String>)> = lock_or_recover(&APP_STATE.mouse_state).drain().collect();
+    let keys: Vec<(Key, Option<String>)> = lock_or_recover(&APP_STATE.key_state).drain().collect();
+    queue_input(None, move |enigo| {
+        for (button, owner) in buttons {
+            enigo.mouse_up(button);
+            log::info!("Emergency release: mouse {:?} (held by {:?})", button, owner);
+        }
+        for (key, owner) in keys {
+            enigo.key_up(key);
+            log::info!("Emergency release: key {:?} (held by {:?})", key, owner);
+        }
+    });
+    log::info!("Emergency release complete: aborted {} pending groups", aborted);
+}
+
+// Kill switch for stuck inputs; unlike cancel_macro this affects every macro
+#[tauri::command]
+fn emergency_release() -> Result<(), String> {
+    release_all_inputs();
     Ok(())
 }
 
+const MACROS_CLEARED_EVENT: &str = "macros-cleared";
+
+// Command to unregister every macro and reset all runtime state. Emptying the registered
+// set first means MIDI arriving meanwhile matches nothing; triggers already in flight are
+// stopped by release_all_inputs advancing their sessions. The session counters themselves
+// are kept so a stale flow can never match a restarted id.
 #[tauri::command]
-fn stop_midi_listening_rust<R: Runtime>(app_handle: AppHandle<R>) -> Result<(), String> {
-    let mut connection_guard = APP_STATE.midi_connection.lock().unwrap();
-    if connection_guard.is_some() {
-        *connection_guard = None;
-        if let Err(e) = app_handle.emit("midi-status", "MIDI connection closed") {
-            eprintln!("Failed to emit MIDI status: {}", e);
-        }
+fn clear_all_macros<R: Runtime>(app_handle: AppHandle<R>) -> Result<usize, String> {
+    let cleared = {
+        let mut macros = lock_or_recover(&APP_STATE.registered_macros);
+        let cleared = macros.len();
+        macros.clear();
+        persist_macros(&macros);
+        cleared
+    };
+
+    release_all_inputs();
+    lock_or_recover(&APP_STATE.last_group_triggers).clear();
+    lock_or_recover(&APP_STATE.last_scroll_triggers).clear();
+    lock_or_recover(&APP_STATE.last_macro_triggers).clear();
+    lock_or_recover(&APP_STATE.macro_toggle_states).clear();
+    lock_or_recover(&APP_STATE.undo_stacks).clear();
+    log::info!("Cleared all {} macros", cleared);
+
+    if let Err(e) = app_handle.emit(MACROS_CLEARED_EVENT, cleared) {
+        log::error!("Failed to emit {} event: {}", MACROS_CLEARED_EVENT, e);
     }
-    Ok(())
+    Ok(cleared)
 }
 
-// MIDI Event coming from Rust
-#[derive(Debug, Clone, Serialize)]
-pub struct RustMidiEvent {
-    pub status: u8,
-    pub data1: u8,
-    pub data2: u8,
-    pub timestamp: u64,
-    pub type_name: String, // "noteon", "noteoff", "controlchange", etc.
-    pub channel: u8,       // 1-16
-    pub note: Option<u8>,  // For noteon/noteoff
-    pub velocity: Option<u8>, // For noteon/noteoff
-    pub controller: Option<u8>, // For controlchange
-    pub value: Option<u8>,     // For controlchange
+const MODIFIER_KEYS: [Key; 4] = [Key::Shift, Key::Control, Key::Alt, Key::Meta];
+
+// Modifiers the OS currently reports as down, or None where that can't be queried
+#[cfg(target_os = "windows")]
+fn os_modifiers_down() -> Option<Vec<Key>> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN, VK_SHIFT};
+
+    let is_down = |vk: u16| unsafe { GetAsyncKeyState(vk as i32) } < 0;
+    let keys = [
+        (Key::Shift, is_down(VK_SHIFT)),
+        (Key::Control, is_down(VK_CONTROL)),
+        (Key::Alt, is_down(VK_MENU)),
+        (Key::Meta, is_down(VK_LWIN) || is_down(VK_RWIN)),
+    ];
+    Some(keys.into_iter().filter(|(_, down)| *down).map(|(key, _)| key).collect())
 }
 
-// Command to get cursor position
-#[tauri::command]
-fn get_cursor_position() -> Result<(i32, i32), String> {
-    let enigo = create_enigo();
-    
-    // Get the mouse position and explicitly create a tuple in (x, y) order
-    // This ensures the coordinates are in the expected order
-    let position = enigo.mouse_location();
-    
-    // Log coordinates for debugging
-    println!("Cursor position: x={}, y={}", position.0, position.1);
-    
-    // Return explicitly as (x, y)
-    Ok((position.0, position.1))
+#[cfg(target_os = "macos")]
+fn os_modifiers_down() -> Option<Vec<Key>> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+    const HID_SYSTEM_STATE: i32 = 1;
+
+    let flags = unsafe { CGEventSourceFlagsState(HID_SYSTEM_STATE) };
+    let keys = [
+        (Key::Shift, 0x0002_0000),
+        (Key::Control, 0x0004_0000),
+        (Key::Alt, 0x0008_0000),
+        (Key::Meta, 0x0010_0000),
+    ];
+    Some(keys.into_iter().filter(|(_, mask)| flags & mask != 0).map(|(key, _)| key).collect())
 }
 
-// Command to get global settings
-#[tauri::command]
-fn get_global_settings() -> Result<GlobalSettings, String> {
-    let settings = APP_STATE.global_settings.lock().unwrap();
-    Ok(settings.clone())
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn os_modifiers_down() -> Option<Vec<Key>> {
+    None
 }
 
-// Command to update global settings
+// Reconcile tracked modifiers with reality: release ones held by a macro that is no
+// longer registered or enabled, and forget ones the OS reports as already released.
+// With release_untracked, modifiers the OS reports down that no macro holds are released
+// too (e.g. left over from an interrupted KeyCombination or a crash); that is only done on
+// request since it would also release a modifier the user is physically holding.
+// Returns a description of each change.
+fn sync_modifiers(release_untracked: bool) -> Vec<String> {
+    let tracks_modifiers = {
+        let key_state = lock_or_recover(&APP_STATE.key_state);
+        MODIFIER_KEYS.iter().any(|key| key_state.contains_key(key))
+    };
+    if !tracks_modifiers && !release_untracked {
+        return Vec::new();
+    }
+
+    let live_macros: HashSet<String> = lock_or_recover(&APP_STATE.registered_macros)
+        .iter()
+        .filter(|m| m.enabled != Some(false))
+        .map(|m| m.id.clone())
+        .collect();
+    let os_down = os_modifiers_down();
+
+    let mut changes = Vec::new();
+    let mut released = Vec::new();
+    let mut key_state = lock_or_recover(&APP_STATE.key_state);
+    for key in MODIFIER_KEYS {
+        let reported_down = os_down.as_ref().map(|down| down.contains(&key));
+        match key_state.get(&key) {
+            Some(owner) if owner.as_ref().is_some_and(|id| !live_macros.contains(id)) => {
+                released.push(key);
+                changes.push(format!("Released {} held by removed or disabled macro {}", key_to_string(key), owner.as_deref().unwrap_or_default()));
+                key_state.remove(&key);
+            },
+            Some(_) if reported_down == Some(false) => {
+                changes.push(format!("Stopped tracking {}, which was already released", key_to_string(key)));
+                key_state.remove(&key);
+            },
+            None if release_untracked && reported_down == Some(true) => {
+                released.push(key);
+                changes.push(format!("Released stuck {}", key_to_string(key)));
+            },
+            _ => {},
+        }
+    }
+    drop(key_state);
+    if !released.is_empty() {
+        queue_input(None, move |enigo| {
+            for key in released {
+                enigo.key_up(key);
+            }
+        });
+    }
+    for change in &changes {
+        log::info!("Modifier sync: {}", change);
+    }
+    changes
+}
+
+// Command to fix stuck modifiers without releasing everything like emergency_release
 #[tauri::command]
-fn update_global_settings(new_settings: GlobalSettings) -> Result<(), String> {
-    let mut settings = APP_STATE.global_settings.lock().unwrap();
-    *settings = new_settings;
-    println!("Global settings updated: {:?}", *settings);
-    Ok(())
+fn sync_modifier_state() -> Result<Vec<String>, String> {
+    Ok(sync_modifiers(true))
 }
+
+// Release only the buttons and keys held by this macro, so other macros keep theirs.
+// This runs on the macro's input thread after the input it already queued, so nothing the
+// macro queued earlier can press a key again once it has been released.
 fn cleanup_mouse_state_for_macro(macro_id: &str) {
-    // You could track which macro pressed which buttons
-    // For now, just ensure all buttons are released
-    let mut enigo = create_enigo();
-    let mut mouse_state = APP_STATE.mouse_state.lock().unwrap();
-    let mut key_state = APP_STATE.key_state.lock().unwrap();
-    
-    for (button, is_pressed) in mouse_state.iter_mut() {
-        if *is_pressed {
+    let owner = macro_id.to_string();
+    queue_input(Some(macro_id), move |enigo| {
+        let macro_id = owner;
+        let mut mouse_state = lock_or_recover(&APP_STATE.mouse_state);
+        let mut key_state = lock_or_recover(&APP_STATE.key_state);
+
+        mouse_state.retain(|button, owner| {
+            if owner.as_deref() != Some(macro_id.as_str()) {
+                return true;
+            }
             enigo.mouse_up(*button);
-            *is_pressed = false;
-            println!("Cleanup: released mouse {:?} for macro {}", button, macro_id);
-        }
-    }
-    
-    for (key, is_pressed) in key_state.iter_mut() {
-        if *is_pressed {
+            log::info!("Cleanup: released mouse {:?} for macro {}", button, macro_id);
+            false
+        });
+
+        key_state.retain(|key, owner| {
+            if owner.as_deref() != Some(macro_id.as_str()) {
+                return true;
+            }
             enigo.key_up(*key);
-            *is_pressed = false;
-            println!("Cleanup: released key {:?} for macro {}", key, macro_id);
-        }
-    }
+            log::info!("Cleanup: released key {:?} for macro {}", key, macro_id);
+            false
+        });
+    });
 }
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .setup(|app| {
-            // Setup logging
-            #[cfg(debug_assertions)]
-            {
-                let handle = app.handle();
-                handle.plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
-      }
+            // Setup logging to stdout and a file in the app log directory. The plugin lets
+            // everything through; GlobalSettings.log_level decides what is actually recorded.
+            app.handle().plugin(
+                tauri_plugin_log::Builder::default()
+                    .targets([
+                        Target::new(TargetKind::Stdout),
+                        Target::new(TargetKind::LogDir { file_name: Some("opengrader".to_string()) }),
+                    ])
+                    .level(log::LevelFilter::Trace)
+                    .build(),
+            )?;
+            if let Err(e) = apply_log_level(&lock_or_recover(&APP_STATE.global_settings).log_level) {
+                log::warn!("{}", e);
+            }
+
+            // Restore macros persisted from the previous session
+            let config_dir = app.path().app_config_dir()?;
+            *lock_or_recover(&APP_STATE.config_dir) = Some(config_dir);
+            if let Err(e) = load_macros() {
+                log::error!("Failed to load persisted macros: {}", e);
+            }
+
+            app.listen_any(EMERGENCY_RELEASE_EVENT, |_| release_all_inputs());
+            spawn_scheduler(app.handle().clone());
+            tauri::async_runtime::spawn(auto_connect(app.handle().clone()));
             
       Ok(())
     })
         .invoke_handler(tauri::generate_handler![
             register_macro,
+            register_macros,
+            set_active_layer,
+            get_active_layer,
+            get_app_info,
+            get_action_history,
+            get_midi_stats,
+            #[cfg(feature = "midi-injection")]
+            inject_midi_message,
+            list_midi_inputs_detailed,
+            start_midi_recording,
+            stop_midi_recording,
+            save_recording,
+            replay_recording,
+            stop_replay,
+            get_auto_connect_status,
+            set_random_seed,
+            get_setting,
+            set_setting,
+            clear_all_macros,
+            run_self_test,
             get_macros,
+            get_macros_by_group,
+            rename_macro,
+            rename_group,
+            reset_group,
+            get_active_macros,
+            load_macros,
+            export_macros,
+            import_macros,
             execute_action,
             // Add the new MIDI commands
             list_midi_inputs_rust,
             start_midi_listening_rust,
             stop_midi_listening_rust,
+            stop_midi_port,
+            list_midi_outputs,
+            connect_midi_output,
+            midi_learn,
             cancel_macro,
+            emergency_release,
+            undo_last_macro,
+            sync_modifier_state,
+            get_input_state,
+            set_macro_enabled,
+            reorder_actions,
+            trigger_macro,
+            pause_schedules,
+            resume_schedules,
+            pause_macros,
+            resume_macros,
             get_cursor_position,
+            list_monitors,
             // Global settings commands
             get_global_settings,
             update_global_settings
         ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that change GlobalSettings hold this, so parallel tests don't see each other's values
+    static SETTINGS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    // Global settings changed for one test, reset to the defaults when dropped
+    struct TestSettings {
+        _guard: tokio::sync::MutexGuard<'static, ()>,
+    }
+
+    impl TestSettings {
+        async fn with(change: impl FnOnce(&mut GlobalSettings)) -> Self {
+            let guard = SETTINGS_LOCK.lock().await;
+            change(&mut lock_or_recover(&APP_STATE.global_settings));
+            TestSettings { _guard: guard }
+        }
+    }
+
+    impl Drop for TestSettings {
+        fn drop(&mut self) {
+            *lock_or_recover(&APP_STATE.global_settings) = GlobalSettings::default();
+        }
+    }
+
+    // Tests that seed the shared RNG hold this, so their random sequences don't interleave
+    static RNG_LOCK: Mutex<()> = Mutex::new(());
+
+    fn test_macro(id: &str) -> MacroConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "config_version": MACRO_CONFIG_VERSION,
+            "name": id,
+            "midi_note": 60,
+            "midi_channel": 1,
+            "actions": [],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn slow_action_times_out_and_the_next_action_still_runs() {
+        let _settings = TestSettings::with(|s| s.action_timeout_ms = 50).await;
+        let app = tauri::test::mock_app();
+        let context = ActionContext::new(&test_macro("slow-action"), "main", None);
+
+        let started = std::time::Instant::now();
+        let slow = ActionParams { duration: Some(5_000), ..Default::default() };
+        let result = execute_action_timed(ActionType::Delay, slow, &context, app.handle()).await;
+        assert_eq!(result, Err("Timed out after 50ms".to_string()));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        // The stuck action's thread was replaced, so the next one doesn't wait for it
+        let quick = ActionParams { duration: Some(10), ..Default::default() };
+        let result = execute_action_timed(ActionType::Delay, quick, &context, app.handle()).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn forbidden_regions_apply_without_an_app_handle() {
+        let region = ForbiddenRegion { x: 0, y: 0, width: 100, height: 100, monitor: None, label: None };
+        let _settings = TestSettings::with(|s| s.forbidden_regions = vec![region]).await;
+        let check = |action_type: ActionType, params: ActionParams| async move {
+            check_forbidden_regions::<tauri::test::MockRuntime>(None, &action_type, &params, None).await
+        };
+
+        let inside = ActionParams { x: Some(10), y: Some(10), ..Default::default() };
+        let outside = ActionParams { x: Some(500), y: Some(500), ..Default::default() };
+        assert!(check(ActionType::MouseMove, inside.clone()).await.is_err());
+        assert!(check(ActionType::MoveAndClick, inside).await.is_err());
+        assert!(check(ActionType::MouseMove, outside.clone()).await.is_ok());
+        let drag_in = ActionParams { end_x: Some(50), end_y: Some(50), ..outside };
+        assert!(check(ActionType::DragTo, drag_in).await.is_err());
+    }
+
+    fn delay_action(duration: u32) -> MacroAction {
+        MacroAction {
+            action_type: ActionType::Delay,
+            action_params: ActionParams { duration: Some(duration), ..Default::default() },
+            velocity_scaling: None,
+            condition: None,
+        }
+    }
+
+    // Load a saved macros file the way load_macros_from_disk does
+    fn load_macros_json(json: &str) -> Result<Vec<MacroConfig>, serde_json::Error> {
+        let mut macros: Vec<MacroConfig> = serde_json::from_str(json)?;
+        macros.iter_mut().for_each(migrate_macro_config);
+        Ok(macros)
+    }
+
+    #[test]
+    fn unversioned_config_is_migrated_and_round_trips() {
+        let json = r#"[{
+            "id": "v0", "name": "Old macro", "midi_note": 64, "midi_channel": 2,
+            "actions": [
+                { "action_type": "MouseMove", "action_params": { "x": 10, "y": 20, "duration": 500 } },
+                { "action_type": "Delay", "action_params": { "duration": 500 } }
+            ],
+            "after_actions": [
+                { "action_type": "MouseMove", "action_params": { "x": 0, "y": 0, "duration": 500 } }
+            ]
+        }]"#;
+        let loaded = load_macros_json(json).unwrap();
+        let config = &loaded[0];
+        assert_eq!(config.config_version, MACRO_CONFIG_VERSION);
+        assert_eq!((config.midi_note, config.midi_channel), (64, 2));
+        // The editor's old MouseMove default is dropped; other durations are kept
+        assert_eq!(config.actions[0].action_params.duration, None);
+        assert_eq!(config.actions[1].action_params.duration, Some(500));
+        assert_eq!(config.after_actions.as_ref().unwrap()[0].action_params.duration, None);
+
+        let saved = serde_json::to_string(&loaded).unwrap();
+        let reloaded = load_macros_json(&saved).unwrap();
+        assert_eq!(serde_json::to_value(&reloaded).unwrap(), serde_json::to_value(&loaded).unwrap());
+    }
+
+    #[test]
+    fn current_config_keeps_mouse_move_duration() {
+        let json = format!(r#"[{{
+            "id": "v{0}", "config_version": {0}, "name": "New macro", "midi_note": 64, "midi_channel": 2,
+            "actions": [{{ "action_type": "MouseMove", "action_params": {{ "x": 10, "y": 20, "duration": 250 }} }}]
+        }}]"#, MACRO_CONFIG_VERSION);
+        let loaded = load_macros_json(&json).unwrap();
+        assert_eq!(loaded[0].actions[0].action_params.duration, Some(250));
+        let reloaded = load_macros_json(&serde_json::to_string(&loaded).unwrap()).unwrap();
+        assert_eq!(reloaded[0].actions[0].action_params.duration, Some(250));
+    }
+
+    #[test]
+    fn newer_config_is_loaded_as_is() {
+        let json = r#"[{
+            "id": "future", "config_version": 99, "name": "Future macro", "midi_note": 64, "midi_channel": 2,
+            "actions": [], "some_future_field": true
+        }]"#;
+        let loaded = load_macros_json(json).unwrap();
+        assert_eq!(loaded[0].config_version, 99);
+    }
+
+    #[test]
+    fn config_without_a_trigger_is_rejected() {
+        let json = r#"[{ "id": "partial", "name": "Partial", "actions": [] }]"#;
+        assert!(load_macros_json(json).is_err());
+        let json = r#"[{ "id": "partial", "midi_note": 60, "actions": [] }]"#;
+        assert!(load_macros_json(json).is_err());
+    }
+
+    #[tokio::test]
+    async fn renaming_a_group_closes_its_session() {
+        let mut grouped = test_macro("rename-member");
+        grouped.groupId = Some("rename-old".to_string());
+        lock_or_recover(&APP_STATE.registered_macros).push(grouped);
+        let session = begin_group_session("rename-old");
+        let pending = tokio::spawn(tokio::time::sleep(std::time::Duration::from_secs(60)));
+        let active = ActiveMacro { abort_handle: pending.abort_handle(), last_triggered: std::time::Instant::now() };
+        lock_or_recover(&APP_STATE.active_macros).insert("rename-old".to_string(), active);
+        let before = BeforeActionState { last_executed: std::time::Instant::now(), cooldown: std::time::Duration::ZERO, triggers: 1 };
+        lock_or_recover(&APP_STATE.before_action_states).insert("rename-old".to_string(), before);
+
+        let app = tauri::test::mock_app();
+        let renamed = rename_group(app.handle().clone(), "rename-old".to_string(), "rename-new".to_string()).await;
+        assert_eq!(renamed, Ok(1));
+
+        assert!(!is_current_session("rename-old", session));
+        assert!(pending.await.unwrap_err().is_cancelled());
+        assert!(!lock_or_recover(&APP_STATE.active_macros).contains_key("rename-old"));
+        for key in ["rename-old", "rename-new"] {
+            assert!(!lock_or_recover(&APP_STATE.before_action_states).contains_key(key));
+        }
+        let macros = lock_or_recover(&APP_STATE.registered_macros);
+        assert!(macros.iter().any(|m| m.id == "rename-member" && m.groupId.as_deref() == Some("rename-new")));
+    }
+
+    #[test]
+    fn cancelling_stops_a_blocking_action_at_its_next_step() {
+        let token = ActionToken::new();
+        let action_token = token.clone();
+        let action = std::thread::spawn(move || {
+            let params = ActionParams { duration: Some(10_000), ..Default::default() };
+            execute_action_with(&mut Enigo::new(), ActionType::Delay, params, None, &action_token)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(action.join().unwrap(), Err("Action was cancelled".to_string()));
+    }
+
+    // JSON payloads of an event the app emits
+    fn collect_events(app: &tauri::App<tauri::test::MockRuntime>, event: &str) -> tokio::sync::mpsc::UnboundedReceiver<serde_json::Value> {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        app.listen_any(event, move |event| {
+            if let Ok(payload) = serde_json::from_str(event.payload()) {
+                let _ = sender.send(payload);
+            }
+        });
+        receiver
+    }
+
+    // The next collected payload that matches, skipping ones caused by other tests
+    async fn next_event(
+        events: &mut tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+        matches: impl Fn(&serde_json::Value) -> bool,
+    ) -> serde_json::Value {
+        let wait = async {
+            loop {
+                let payload = events.recv().await.expect("listener was dropped");
+                if matches(&payload) {
+                    return payload;
+                }
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), wait).await.expect("event was not emitted")
+    }
+
+    #[tokio::test]
+    async fn injected_note_triggers_its_macro_and_reaches_the_monitor() {
+        let _settings = TestSettings::with(|_| {}).await;
+        let mut config = test_macro("inject-member");
+        (config.midi_note, config.midi_channel) = (100, 16);
+        lock_or_recover(&APP_STATE.registered_macros).push(config);
+        let app = tauri::test::mock_app();
+        let mut triggers = collect_events(&app, "macro-trigger");
+        let mut midi_events = collect_events(&app, "rust-midi-event");
+
+        let before = midi_timestamp();
+        assert_eq!(inject_midi_message(app.handle().clone(), vec![0x9F, 100, 127]), Ok(()));
+
+        let trigger = next_event(&mut triggers, |t| t["macro_id"] == "inject-member").await;
+        assert_eq!(trigger["macro_name"], "inject-member");
+        let event = next_event(&mut midi_events, |e| e["channel"] == 16 && e["note"] == 100).await;
+        assert_eq!((event["type_name"].as_str(), event["velocity"].as_u64()), (Some("noteon"), Some(127)));
+        // Stamped from the same clock as live input, not wall-clock time
+        let timestamp = event["timestamp"].as_u64().unwrap();
+        assert!(timestamp >= before && timestamp <= midi_timestamp());
+    }
+
+    #[test]
+    fn injecting_no_bytes_is_rejected() {
+        let app = tauri::test::mock_app();
+        assert!(inject_midi_message(app.handle().clone(), vec![]).is_err());
+    }
+
+    #[tokio::test]
+    async fn injected_and_live_messages_emit_the_same_events() {
+        let _settings = TestSettings::with(|_| {}).await;
+        let mut config = test_macro("equivalence-member");
+        (config.midi_note, config.midi_channel) = (101, 16);
+        lock_or_recover(&APP_STATE.registered_macros).push(config);
+        let app = tauri::test::mock_app();
+        let mut triggers = collect_events(&app, "macro-trigger");
+        let mut midi_events = collect_events(&app, "rust-midi-event");
+
+        let messages = [
+            vec![0x9F, 101, 90],
+            vec![0x8F, 101, 0],
+            vec![0xBF, 70, 64],
+            vec![0xEF, 0x00, 0x50],
+            vec![0xDF, 40],
+            vec![0xF0, 0x7D, 0x01, 0xF7],
+        ];
+        for bytes in messages {
+            let parsed = parse_midi_message(&bytes).unwrap();
+            let same_message = |e: &serde_json::Value| e["status"] == parsed.status && e["data1"] == parsed.data1;
+            handle_incoming_midi(&bytes, midi_timestamp(), app.handle());
+            let mut live = next_event(&mut midi_events, same_message).await;
+            assert_eq!(inject_midi_message(app.handle().clone(), bytes.clone()), Ok(()));
+            let mut injected = next_event(&mut midi_events, same_message).await;
+            for event in [&mut live, &mut injected] {
+                event.as_object_mut().unwrap().remove("timestamp");
+            }
+            assert_eq!(injected, live, "events differ for {:02X?}", bytes);
+        }
+
+        // The NoteOn triggered the macro from both paths
+        for _ in 0..2 {
+            next_event(&mut triggers, |t| t["macro_id"] == "equivalence-member").await;
+        }
+    }
+
+    #[test]
+    fn saved_macros_load_back_unchanged() {
+        let dir = std::env::temp_dir().join(format!("opengrader-test-{}", std::process::id()));
+        let path = dir.join(MACROS_FILE_NAME);
+        assert_eq!(read_macros_file(&path).map(|m| m.len()), Ok(0));
+
+        let detailed: MacroConfig = serde_json::from_value(serde_json::json!({
+            "id": "saved-detailed", "config_version": MACRO_CONFIG_VERSION, "name": "Detailed", "midi_note": 0, "midi_channel": 0,
+            "midi_note_min": 36, "midi_note_max": 51, "groupId": "saved-group", "timeout": 750,
+            "actions": [
+                { "action_type": "KeyCombination", "action_params": { "keys": ["control", "shift", "tab"], "hold_duration_ms": 40 } },
+                { "action_type": "Delay", "action_params": { "duration": 100, "jitter_ms": 20, "probability": 0.5 } }
+            ],
+            "after_actions": [{ "action_type": "MouseClick", "action_params": { "button": "back", "amount": 2 } }],
+        }))
+        .unwrap();
+        let macros = vec![detailed, test_macro("saved-plain")];
+        write_macros_file(&path, &macros).unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+        let loaded = read_macros_file(&path).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&macros).unwrap());
+
+        // Saving again replaces the whole set
+        write_macros_file(&path, &macros[1..]).unwrap();
+        let loaded = read_macros_file(&path).unwrap();
+        assert_eq!(loaded.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), ["saved-plain"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn poisoned_mutex_is_recovered() {
+        let mutex = Mutex::new(vec![1]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut values = mutex.lock().unwrap();
+            values.push(2);
+            panic!("panicking while holding the lock");
+        }));
+        assert!(result.is_err() && mutex.is_poisoned());
+
+        // The data written before the panic is still there and later locks keep working
+        lock_or_recover(&mutex).push(3);
+        assert_eq!(*lock_or_recover(&mutex), [1, 2, 3]);
+    }
+
+    fn midi(bytes: &[u8]) -> MidiData {
+        parse_midi_message(bytes).unwrap()
+    }
+
+    #[test]
+    fn wildcard_channel_matches_a_note_on_every_channel() {
+        let specific = test_macro("channel-specific");
+        let mut wildcard = test_macro("channel-wildcard");
+        wildcard.midi_channel = MIDI_CHANNEL_ANY;
+
+        for status in [0x90, 0x93, 0x9F] {
+            let note = midi(&[status, 60, 100]);
+            assert!(should_trigger_macro(&wildcard, &note));
+            assert_eq!(should_trigger_macro(&specific, &note), note.channel == 1);
+        }
+    }
+
+    #[test]
+    fn note_range_macro_matches_its_inclusive_range() {
+        let mut octave = test_macro("note-range");
+        (octave.midi_note_min, octave.midi_note_max) = (Some(60), Some(72));
+
+        for status in [0x90, 0x80] {
+            for note in [60, 66, 72] {
+                assert!(should_trigger_macro(&octave, &midi(&[status, note, 100])));
+            }
+            for note in [59, 73] {
+                assert!(!should_trigger_macro(&octave, &midi(&[status, note, 100])));
+            }
+        }
+
+        // With only one bound the exact midi_note is used
+        octave.midi_note_max = None;
+        assert!(should_trigger_macro(&octave, &midi(&[0x90, 60, 100])));
+        assert!(!should_trigger_macro(&octave, &midi(&[0x90, 66, 100])));
+    }
+
+    #[test]
+    fn seeded_jitter_is_repeatable_and_stays_in_range() {
+        let _rng = lock_or_recover(&RNG_LOCK);
+        let params = ActionParams { duration: Some(100), jitter_ms: Some(20), ..Default::default() };
+        let delays = |seed| {
+            set_random_seed(Some(seed));
+            (0..100).map(|_| delay_duration(&params).unwrap().as_millis()).collect::<Vec<_>>()
+        };
+
+        let first = delays(7);
+        assert_eq!(first, delays(7));
+        assert_ne!(first, delays(8));
+        assert!(first.iter().all(|ms| (80..=120).contains(ms)));
+        assert!(first.iter().any(|ms| *ms != 100));
+        set_random_seed(None);
+    }
+
+    #[test]
+    fn media_key_names_resolve() {
+        for name in MEDIA_KEY_NAMES {
+            assert_eq!(string_to_key(name), media_key(name));
+            assert_eq!(string_to_key(&name.to_uppercase()), media_key(name));
+            // macOS has keycodes only for the volume keys
+            if cfg!(not(target_os = "macos")) || name.starts_with("volume") {
+                assert!(string_to_key(name).is_some(), "{} did not resolve", name);
+            }
+        }
+    }
+
+    #[test]
+    fn one_two_and_three_byte_messages_parse() {
+        let parsed = |bytes: &[u8]| {
+            let data = midi(bytes);
+            (data.message_type, data.channel, data.data1, data.data2)
+        };
+        assert!(parse_midi_message(&[]).is_none());
+
+        // One byte: real-time messages have no channel or data
+        assert_eq!(parsed(&[0xF8]), (MidiMessageType::Other, 0, 0, 0));
+        assert_eq!(parsed(&[0xFF]), (MidiMessageType::Other, 0, 0, 0));
+
+        // Two bytes: Program Change and Channel Pressure; bytes past the status's length are ignored
+        assert_eq!(parsed(&[0xC3, 5]), (MidiMessageType::ProgramChange, 4, 5, 0));
+        assert_eq!(parsed(&[0xC3, 5, 99]), (MidiMessageType::ProgramChange, 4, 5, 0));
+        assert_eq!(parsed(&[0xD0, 64]), (MidiMessageType::ChannelPressure, 1, 64, 0));
+
+        // Three bytes, and a truncated one read with a missing velocity of 0
+        assert_eq!(parsed(&[0x90, 60, 100]), (MidiMessageType::NoteOn, 1, 60, 100));
+        assert_eq!(parsed(&[0xBF, 7, 127]), (MidiMessageType::ControlChange, 16, 7, 127));
+        assert_eq!(parsed(&[0x91, 60]), (MidiMessageType::NoteOff, 2, 60, 0));
+
+        let sysex = midi(&[0xF0, 0x7D, 0x01, 0xF7]);
+        assert_eq!((sysex.message_type, sysex.channel), (MidiMessageType::SysEx, 0));
+        assert_eq!(sysex.sysex.as_deref(), Some(&[0xF0, 0x7D, 0x01, 0xF7][..]));
+    }
+
+    #[test]
+    fn relative_encoder_values_decode_to_signed_deltas() {
+        use EncoderMode::*;
+        for (value, twos_complement, signed_bit) in [(0, 0, 0), (1, 1, 1), (63, 63, 63), (65, -63, -1), (127, -1, -63)] {
+            assert_eq!(decode_relative_cc(RelativeTwosComplement, value), Some(twos_complement));
+            assert_eq!(decode_relative_cc(RelativeSignedBit, value), Some(signed_bit));
+            assert_eq!(decode_relative_cc(Absolute, value), None);
+        }
+    }
+
+    #[test]
+    fn relative_encoder_picks_increment_or_decrement_actions() {
+        let mut jog = test_macro("encoder-jog");
+        (jog.midi_note, jog.encoder_mode) = (20, Some(EncoderMode::RelativeTwosComplement));
+        jog.increment_actions = Some(vec![delay_action(1)]);
+        jog.decrement_actions = Some(vec![delay_action(2)]);
+
+        let clockwise = midi(&[0xB0, 20, 1]);
+        let counter_clockwise = midi(&[0xB0, 20, 127]);
+        assert!(should_trigger_macro(&jog, &clockwise) && should_trigger_macro(&jog, &counter_clockwise));
+        assert!(!should_trigger_macro(&jog, &midi(&[0xB0, 20, 0])));
+        assert!(!should_trigger_macro(&jog, &midi(&[0xB0, 21, 1])));
+
+        let (phase, actions) = select_main_actions(&jog, Some(&clockwise));
+        assert_eq!((phase, actions[0].action_params.duration), ("increment", Some(1)));
+        let (phase, actions) = select_main_actions(&jog, Some(&counter_clockwise));
+        assert_eq!((phase, actions[0].action_params.duration), ("decrement", Some(2)));
+    }
+
+    #[tokio::test]
+    async fn after_actions_without_a_timeout_run_after_the_default_one() {
+        let _settings = TestSettings::with(|s| s.default_timeout = 100).await;
+        let mut config = test_macro("default-timeout");
+        config.after_actions = Some(vec![delay_action(1)]);
+        let app = tauri::test::mock_app();
+        let mut completions = collect_events(&app, "after-actions-complete");
+
+        let started = std::time::Instant::now();
+        handle_macro_trigger(config, None, app.handle().clone()).await;
+        let completion = next_event(&mut completions, |e| e["macro_id"] == "default-timeout").await;
+        assert_eq!(completion["aborted"], false);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+
+        // Nothing is scheduled for a macro without after_actions
+        assert_eq!(after_actions_timeout(&test_macro("default-timeout")), None);
+    }
+
+    #[test]
+    fn cc_value_matches_within_its_tolerance() {
+        let mut fader = test_macro("cc-tolerance");
+        (fader.midi_note, fader.midi_value) = (7, Some(64));
+        let matches = |fader: &MacroConfig, value| should_trigger_macro(fader, &midi(&[0xB0, 7, value]));
+
+        // No tolerance and a tolerance of 0 both mean an exact match
+        for tolerance in [None, Some(0)] {
+            fader.midi_value_tolerance = tolerance;
+            assert!(matches(&fader, 64));
+            assert!(!matches(&fader, 63) && !matches(&fader, 65));
+        }
+
+        fader.midi_value_tolerance = Some(2);
+        assert!([62, 63, 64, 65, 66].into_iter().all(|value| matches(&fader, value)));
+        assert!(!matches(&fader, 61) && !matches(&fader, 67));
+
+        // Near the ends of the CC range the window is cut off rather than wrapping
+        (fader.midi_value, fader.midi_value_tolerance) = (Some(1), Some(2));
+        assert!(matches(&fader, 0) && matches(&fader, 3) && !matches(&fader, 4));
+        (fader.midi_value, fader.midi_value_tolerance) = (Some(127), Some(255));
+        assert!(matches(&fader, 0) && matches(&fader, 127));
+    }
+
+    #[test]
+    fn cc_value_zones_match_inclusive_ranges() {
+        let zone = |id: &str, min, max| {
+            let mut config = test_macro(id);
+            (config.midi_note, config.midi_value_min, config.midi_value_max) = (7, min, max);
+            config
+        };
+        let matching = |zones: &[&MacroConfig], value| {
+            zones.iter().filter(|z| should_trigger_macro(z, &midi(&[0xB0, 7, value]))).map(|z| z.id.clone()).collect::<Vec<_>>()
+        };
+
+        // Adjacent zones split the fader with no gap or overlap
+        let (lower, upper) = (zone("zone-lower", Some(0), Some(84)), zone("zone-upper", Some(85), Some(127)));
+        assert_eq!(matching(&[&lower, &upper], 84), ["zone-lower"]);
+        assert_eq!(matching(&[&lower, &upper], 85), ["zone-upper"]);
+        assert_eq!(matching(&[&lower, &upper], 0), ["zone-lower"]);
+        assert_eq!(matching(&[&lower, &upper], 127), ["zone-upper"]);
+
+        // Overlapping zones both fire in the shared part; a missing bound is open-ended
+        let (low_mid, high_mid) = (zone("zone-low-mid", None, Some(70)), zone("zone-high-mid", Some(60), None));
+        assert_eq!(matching(&[&low_mid, &high_mid], 65), ["zone-low-mid", "zone-high-mid"]);
+        assert_eq!(matching(&[&low_mid, &high_mid], 59), ["zone-low-mid"]);
+        assert_eq!(matching(&[&low_mid, &high_mid], 71), ["zone-high-mid"]);
+
+        // Zones only apply to their own controller
+        assert!(!should_trigger_macro(&upper, &midi(&[0xB0, 8, 100])));
+    }
+
+    // Records the button presses made through it instead of sending them
+    #[derive(Default)]
+    struct MouseRecorder {
+        calls: Vec<(&'static str, MouseButton)>,
+    }
+
+    impl MouseControllable for MouseRecorder {
+        fn mouse_move_to(&mut self, _x: i32, _y: i32) {}
+        fn mouse_move_relative(&mut self, _x: i32, _y: i32) {}
+        fn mouse_down(&mut self, button: MouseButton) {
+            self.calls.push(("down", button));
+        }
+        fn mouse_up(&mut self, button: MouseButton) {
+            self.calls.push(("up", button));
+        }
+        fn mouse_click(&mut self, button: MouseButton) {
+            self.calls.push(("click", button));
+        }
+        fn mouse_scroll_x(&mut self, _length: i32) {}
+        fn mouse_scroll_y(&mut self, _length: i32) {}
+        fn main_display_size(&self) -> (i32, i32) {
+            (1920, 1080)
+        }
+        fn mouse_location(&self) -> (i32, i32) {
+            (0, 0)
+        }
+    }
+
+    #[test]
+    fn click_count_clicks_that_many_times() {
+        // An absent or invalid count is a single click
+        for (count, clicks) in [(1, 1), (2, 2), (3, 3), (0, 1), (-2, 1)] {
+            let mut mouse = MouseRecorder::default();
+            click_n(&mut mouse, MouseButton::Middle, count);
+            assert_eq!(mouse.calls, vec![("click", MouseButton::Middle); clicks]);
+        }
+
+        // A button a hold has down is released before the clicks
+        lock_or_recover(&APP_STATE.mouse_state).insert(MouseButton::Middle, None);
+        let mut mouse = MouseRecorder::default();
+        click_n(&mut mouse, MouseButton::Middle, 2);
+        assert_eq!(mouse.calls, [("up", MouseButton::Middle), ("click", MouseButton::Middle), ("click", MouseButton::Middle)]);
+        assert!(!lock_or_recover(&APP_STATE.mouse_state).contains_key(&MouseButton::Middle));
+    }
+
+    #[tokio::test]
+    async fn held_combination_presses_waits_then_releases() {
+        let _settings = TestSettings::with(|_| {}).await;
+        let app = tauri::test::mock_app();
+        let context = ActionContext::new(&test_macro("held-combination"), "main", None);
+        let keys = ["f13", "f14", "f15"].map(String::from);
+        let hold = std::time::Duration::from_millis(100);
+        let held_count = || {
+            let key_state = lock_or_recover(&APP_STATE.key_state);
+            keys.iter().filter(|name| key_state.contains_key(&string_to_key(name).unwrap())).count()
+        };
+
+        let started = std::time::Instant::now();
+        let holding = hold_keys(&keys, hold, ActionType::KeyCombination, &context, 0, app.handle());
+        let midway = async {
+            tokio::time::sleep(hold / 2).await;
+            held_count()
+        };
+        let (completed, held_midway) = tokio::join!(holding, midway);
+        assert!(completed);
+        assert_eq!(held_midway, keys.len());
+        assert!(started.elapsed() >= hold);
+        assert_eq!(held_count(), 0);
+
+    }
+
+    #[test]
+    fn velocity_curve_remaps_between_the_fixed_ends() {
+        assert_eq!(remap_velocity(64, 0.5), 90);
+        assert_eq!(remap_velocity(64, 2.0), 32);
+        for gamma in [0.5, 2.0, 4.0] {
+            assert_eq!((remap_velocity(0, gamma), remap_velocity(127, gamma)), (0, 127));
+            // Soft hits stay hits instead of becoming NoteOffs
+            assert!(remap_velocity(1, gamma) >= 1);
+            assert!((1..127).all(|v| remap_velocity(v, gamma) <= remap_velocity(v + 1, gamma)));
+        }
+        for gamma in [1.0, 0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!((0..=127).all(|v| remap_velocity(v, gamma) == v));
+        }
+    }
+
+    #[test]
+    fn seeded_probability_runs_actions_at_their_rate() {
+        let _rng = lock_or_recover(&RNG_LOCK);
+        let rolls = |seed, probability| {
+            set_random_seed(Some(seed));
+            (0..10_000).map(|_| passes_probability(probability)).collect::<Vec<_>>()
+        };
+
+        let first = rolls(42, Some(0.3));
+        assert_eq!(first, rolls(42, Some(0.3)));
+        let ran = first.iter().filter(|ran| **ran).count();
+        assert!((2_800..=3_200).contains(&ran), "ran {} of 10000", ran);
+
+        assert!(rolls(42, None).into_iter().all(|ran| ran));
+        assert!(rolls(42, Some(1.0)).into_iter().all(|ran| ran));
+        assert!(rolls(42, Some(0.0)).into_iter().all(|ran| !ran));
+        set_random_seed(None);
+    }
+
+    #[test]
+    fn side_mouse_button_names_resolve() {
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        for (names, button) in [(["back", "x1", "Back"], MouseButton::Back), (["forward", "x2", "X2"], MouseButton::Forward)] {
+            assert!(names.iter().all(|name| string_to_mouse_button(name) == Some(button)));
+        }
+        #[cfg(target_os = "macos")]
+        for (names, number) in [(["back", "x1", "Back"], 3), (["forward", "x2", "X2"], 4)] {
+            assert!(names.iter().all(|name| side_button_number(name) == Some(number)));
+        }
+        assert_eq!(string_to_mouse_button("x3"), None);
+    }
+
+    #[tokio::test]
+    async fn importing_over_a_grouped_macro_stops_its_flow() {
+        let mut grouped = test_macro("import-member");
+        (grouped.midi_note, grouped.midi_channel, grouped.groupId) = (103, 16, Some("import-group".to_string()));
+        lock_or_recover(&APP_STATE.registered_macros).push(grouped.clone());
+        let session = begin_group_session("import-group");
+        let pending = tokio::spawn(tokio::time::sleep(std::time::Duration::from_secs(60)));
+        let active = ActiveMacro { abort_handle: pending.abort_handle(), last_triggered: std::time::Instant::now() };
+        lock_or_recover(&APP_STATE.active_macros).insert("import-group".to_string(), active);
+        let before = BeforeActionState { last_executed: std::time::Instant::now(), cooldown: std::time::Duration::ZERO, triggers: 1 };
+        lock_or_recover(&APP_STATE.before_action_states).insert("import-group".to_string(), before);
+        lock_or_recover(&APP_STATE.macro_toggle_states).insert("import-member".to_string(), true);
+
+        grouped.name = "Imported".to_string();
+        let path = std::env::temp_dir().join(format!("opengrader-import-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&[grouped]).unwrap()).unwrap();
+        let imported = import_macros(path.to_string_lossy().into_owned(), true);
+        let _ = std::fs::remove_file(&path);
+        assert!(imported.unwrap().iter().any(|m| m.id == "import-member" && m.name == "Imported"));
+
+        assert!(!is_current_session("import-group", session));
+        assert!(pending.await.unwrap_err().is_cancelled());
+        assert!(!lock_or_recover(&APP_STATE.active_macros).contains_key("import-group"));
+        assert!(!lock_or_recover(&APP_STATE.before_action_states).contains_key("import-group"));
+        assert!(!lock_or_recover(&APP_STATE.macro_toggle_states).contains_key("import-member"));
+    }
+
+    #[tokio::test]
+    async fn run_actions_records_the_steps_it_runs_itself() {
+        let _settings = TestSettings::with(|_| {}).await;
+        let app = tauri::test::mock_app();
+        let context = ActionContext::new(&test_macro("history-steps"), "main", None);
+        let missing_key = MacroAction { action_type: ActionType::KeyHoldFor, ..delay_action(0) };
+        assert!(run_actions(&[delay_action(1), missing_key, delay_action(1)], &context, app.handle()).await);
+
+        let steps: Vec<(String, Option<String>)> = lock_or_recover(&APP_STATE.action_history).iter()
+            .filter(|entry| entry.macro_id == "history-steps")
+            .map(|entry| (format!("{:?}", entry.action_type), entry.error.clone()))
+            .collect();
+        let missing = Some("Missing key parameter for KeyHoldFor".to_string());
+        assert_eq!(steps, [("Delay".to_string(), None), ("KeyHoldFor".to_string(), missing), ("Delay".to_string(), None)]);
+    }
+
+    #[test]
+    fn move_and_click_is_undone_by_moving_back() {
+        let params = ActionParams { x: Some(500), y: Some(400), button: Some("left".to_string()), ..Default::default() };
+        let token = ActionToken::new();
+        assert!(inverse_action(&ActionType::MoveAndClick, &params, &token).is_none());
+
+        token.record_cursor((10, 20));
+        let (inverse_type, inverse) = inverse_action(&ActionType::MoveAndClick, &params, &token).unwrap();
+        assert!(matches!(inverse_type, ActionType::MouseMove));
+        assert_eq!((inverse.x, inverse.y, inverse.button), (Some(10), Some(20), None));
+    }
+}
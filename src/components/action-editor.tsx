@@ -160,7 +160,7 @@ export const ActionEditor: React.FC<ActionEditorProps> = ({ action, onSave, onCa
       case "mouserelease":
         return { button: "left" };
       case "mousemove":
-        return { x: 0, y: 0, relative: false, duration: 500 };
+        return { x: 0, y: 0, relative: false };
       case "delay":
         return { duration: 500 };
       default:
@@ -313,6 +313,8 @@ export const ActionEditor: React.FC<ActionEditorProps> = ({ action, onSave, onCa
               <SelectItem key="left">Left Click</SelectItem>
               <SelectItem key="right">Right Click</SelectItem>
               <SelectItem key="middle">Middle Click</SelectItem>
+              <SelectItem key="back">Back (Side Button)</SelectItem>
+              <SelectItem key="forward">Forward (Side Button)</SelectItem>
               <SelectItem key="scroll-up">Scroll Up</SelectItem>
               <SelectItem key="scroll-down">Scroll Down</SelectItem>
             </Select>
@@ -405,6 +407,8 @@ export const ActionEditor: React.FC<ActionEditorProps> = ({ action, onSave, onCa
               <SelectItem key="left">Left Button</SelectItem>
               <SelectItem key="right">Right Button</SelectItem>
               <SelectItem key="middle">Middle Button</SelectItem>
+              <SelectItem key="back">Back Button</SelectItem>
+              <SelectItem key="forward">Forward Button</SelectItem>
             </Select>
           </>
         );
@@ -968,6 +968,7 @@ export const MacrosList: React.FC<MacrosListProps> = ({ onEditMacro, onCreateTem
             midi_note: macroToActivate.trigger.controller || macroToActivate.trigger.note || 0,
             midi_channel: macroToActivate.trigger.channel || 0,
             midi_value: macroToActivate.trigger.type === "controlchange" ? macroToActivate.trigger.value : undefined,
+            trigger_on: macroToActivate.trigger.type === "noteon" ? "NoteOn" : macroToActivate.trigger.type === "noteoff" ? "NoteOff" : undefined,
             actions: macroToActivate.actions.map(convertAction),
             // Include before actions if they exist
             before_actions: macroToActivate.beforeActions && macroToActivate.beforeActions.length > 0 
@@ -1354,6 +1355,7 @@ export const MacrosList: React.FC<MacrosListProps> = ({ onEditMacro, onCreateTem
               midi_note: macroToActivate.trigger.controller || macroToActivate.trigger.note || 0,
               midi_channel: macroToActivate.trigger.channel || 0,
               midi_value: macroToActivate.trigger.type === "controlchange" ? macroToActivate.trigger.value : undefined,
+              trigger_on: macroToActivate.trigger.type === "noteon" ? "NoteOn" : macroToActivate.trigger.type === "noteoff" ? "NoteOff" : undefined,
               actions: macroToActivate.actions.map(convertAction),
               before_actions: macroToActivate.beforeActions && macroToActivate.beforeActions.length > 0 
                 ? macroToActivate.beforeActions.map(convertAction)
@@ -1676,9 +1678,9 @@ export const MacrosList: React.FC<MacrosListProps> = ({ onEditMacro, onCreateTem
         return `Button: ${action.params.button}`;
       case "mousemove":
         if (action.params.relative) {
-          return `Direction: ${action.params.direction || 'right'}\nDistance: ${action.params.distance || 100}px\nDuration: ${action.params.duration || 500}ms`;
+          return `Direction: ${action.params.direction || 'right'}\nDistance: ${action.params.distance || 100}px`;
         } else {
-          return `Position: (${action.params.x}, ${action.params.y})\nDuration: ${action.params.duration || 500}ms`;
+          return `Position: (${action.params.x}, ${action.params.y})`;
         }
       case "mousedrag":
         return `Direction: ${action.params.direction}\nDistance: ${action.params.distance}px\nDuration: ${action.params.duration || 500}ms`;
@@ -2156,6 +2158,7 @@ export const MacrosList: React.FC<MacrosListProps> = ({ onEditMacro, onCreateTem
       midi_note: macro.trigger.controller || macro.trigger.note || 0,
       midi_channel: macro.trigger.channel || 0,
       midi_value: macro.trigger.type === "controlchange" ? macro.trigger.value : undefined,
+      trigger_on: macro.trigger.type === "noteon" ? "NoteOn" : macro.trigger.type === "noteoff" ? "NoteOff" : undefined,
       actions: macro.actions.map(convertAction),
       // Include before actions if they exist
       before_actions: macro.beforeActions && macro.beforeActions.length > 0 
@@ -146,7 +146,7 @@ export const MidiLog = () => {
           </span>
           {event.type === "noteon" && (
             <span className="text-xs px-1 py-0.5 rounded bg-success-100 text-success-700">
-              {event.value}
+              {event.rawVelocity != null ? `${event.rawVelocity} → ${event.value}` : event.value}
             </span>
           )}
         </div>
@@ -160,8 +160,19 @@ export const MidiLog = () => {
           <span className="text-xs px-1 py-0.5 rounded bg-primary-100 text-primary-700">
             {event.value}
           </span>
+          {event.nrpn && (
+            <span className="text-xs px-1 py-0.5 rounded bg-secondary-100 text-secondary-700">
+              NRPN {event.nrpn.param} = {event.nrpn.value}
+            </span>
+          )}
         </div>
       );
+    } else if (event.type === "sysex") {
+      return (
+        <span className="font-mono text-foreground-700 break-all">
+          SysEx {event.sysex}
+        </span>
+      );
     }
     return null;
   };
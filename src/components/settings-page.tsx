@@ -1,7 +1,11 @@
 import React, { useState, useEffect } from "react";
-import { Card, CardHeader, CardBody, Input, Switch, Button, Divider, Slider } from "@heroui/react";
+import { Card, CardHeader, CardBody, Input, Switch, Button, Divider, Slider, Select, SelectItem } from "@heroui/react";
 import { Icon } from "@iconify/react";
 import { useSettings } from "../hooks/use-settings";
+import { ForbiddenRegion, LogLevel } from "../types/settings";
+
+const LOG_LEVELS: LogLevel[] = ["off", "error", "warn", "info", "debug", "trace"];
+const MIDI_MESSAGE_TYPES = ["noteon", "noteoff", "controlchange", "programchange", "pitchbend", "aftertouch", "channelpressure", "sysex", "other"];
 
 export const SettingsPage: React.FC = () => {
   const { settings, updateSettings, resetSettings, isLoading } = useSettings();
@@ -111,6 +115,364 @@ export const SettingsPage: React.FC = () => {
             />
           </div>
 
+          {/* Input Init Delay */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Input Init Delay</h4>
+                <p className="text-xs text-default-500">
+                  Wait before each simulated input; raise only if actions are sometimes dropped
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">{settings.enigoInitDelayMs}ms</span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={10}
+              minValue={0}
+              maxValue={200}
+              value={settings.enigoInitDelayMs}
+              onChange={(value) => updateSettings({ enigoInitDelayMs: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Action Timeout */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Action Timeout</h4>
+                <p className="text-xs text-default-500">
+                  Skip a macro action that takes longer than this and release the keys it holds
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.actionTimeoutMs === 0 ? "Off" : `${settings.actionTimeoutMs / 1000}s`}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={1000}
+              minValue={0}
+              maxValue={120000}
+              value={settings.actionTimeoutMs}
+              onChange={(value) => updateSettings({ actionTimeoutMs: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Max Concurrent Macros */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Max Running Macros</h4>
+                <p className="text-xs text-default-500">
+                  Drop new triggers while this many macros run, protecting against flooding controllers
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.maxConcurrentMacros === 0 ? "Unlimited" : settings.maxConcurrentMacros}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={1}
+              minValue={0}
+              maxValue={64}
+              value={settings.maxConcurrentMacros}
+              onChange={(value) => updateSettings({ maxConcurrentMacros: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Default Move Duration */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Mouse Move Animation</h4>
+                <p className="text-xs text-default-500">
+                  Glide the cursor to mouse move targets instead of jumping, for remote desktops that drop fast jumps
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.defaultMoveDurationMs === 0 ? "Off" : `${settings.defaultMoveDurationMs}ms`}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={10}
+              minValue={0}
+              maxValue={1000}
+              value={settings.defaultMoveDurationMs}
+              onChange={(value) => updateSettings({ defaultMoveDurationMs: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Action History */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Action History Size</h4>
+                <p className="text-xs text-default-500">
+                  How many recently executed actions are kept for the activity feed
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.actionHistorySize === 0 ? "Off" : settings.actionHistorySize}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={50}
+              minValue={0}
+              maxValue={1000}
+              value={settings.actionHistorySize}
+              onChange={(value) => updateSettings({ actionHistorySize: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Log Level */}
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">Log Level</h4>
+              <p className="text-xs text-default-500">
+                Written to the console and the app log file; "debug" includes MIDI traffic
+              </p>
+            </div>
+            <Select
+              aria-label="Log level"
+              size="sm"
+              className="w-32"
+              selectedKeys={[settings.logLevel]}
+              onChange={(e) => e.target.value && updateSettings({ logLevel: e.target.value as LogLevel })}
+            >
+              {LOG_LEVELS.map((level) => (
+                <SelectItem key={level}>{level}</SelectItem>
+              ))}
+            </Select>
+          </div>
+
+          {/* Velocity Curve */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Velocity Curve</h4>
+                <p className="text-xs text-default-500">
+                  Reshape NoteOn velocity before matching: below 1 makes soft hits louder, above 1 makes them softer
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.velocityGamma === 1 ? "Linear" : settings.velocityGamma.toFixed(2)}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={0.05}
+              minValue={0.25}
+              maxValue={3}
+              value={settings.velocityGamma}
+              onChange={(value) => updateSettings({ velocityGamma: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Scroll Acceleration */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Scroll Acceleration</h4>
+                <p className="text-xs text-default-500">
+                  Scroll further per tick when an encoder is spun fast; 1x turns acceleration off
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.scrollAcceleration === 1 ? "Off" : `${settings.scrollAcceleration.toFixed(1)}x`}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={0.5}
+              minValue={1}
+              maxValue={10}
+              value={settings.scrollAcceleration}
+              onChange={(value) => updateSettings({ scrollAcceleration: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {settings.scrollAcceleration > 1 && (
+            <>
+              <div className="space-y-3">
+                <div className="flex justify-between items-center">
+                  <div>
+                    <h4 className="text-sm font-medium">Acceleration Window</h4>
+                    <p className="text-xs text-default-500">
+                      Ticks closer together than this count as a fast spin
+                    </p>
+                  </div>
+                  <div className="text-right">
+                    <span className="text-sm font-mono">{settings.scrollAccelerationWindowMs}ms</span>
+                  </div>
+                </div>
+                <Slider
+                  size="sm"
+                  step={10}
+                  minValue={20}
+                  maxValue={500}
+                  value={settings.scrollAccelerationWindowMs}
+                  onChange={(value) => updateSettings({ scrollAccelerationWindowMs: Array.isArray(value) ? value[0] : value })}
+                  className="max-w-md"
+                />
+              </div>
+
+              <div className="space-y-3">
+                <div className="flex justify-between items-center">
+                  <div>
+                    <h4 className="text-sm font-medium">Acceleration Curve</h4>
+                    <p className="text-xs text-default-500">
+                      1 ramps up evenly with spin speed; higher values only accelerate the fastest spins
+                    </p>
+                  </div>
+                  <div className="text-right">
+                    <span className="text-sm font-mono">{settings.scrollAccelerationCurve.toFixed(1)}</span>
+                  </div>
+                </div>
+                <Slider
+                  size="sm"
+                  step={0.1}
+                  minValue={0.5}
+                  maxValue={4}
+                  value={settings.scrollAccelerationCurve}
+                  onChange={(value) => updateSettings({ scrollAccelerationCurve: Array.isArray(value) ? value[0] : value })}
+                  className="max-w-md"
+                />
+              </div>
+            </>
+          )}
+
+          {/* MIDI Client Name */}
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">MIDI Client Name</h4>
+              <p className="text-xs text-default-500">
+                How openGRADER appears in your DAW's MIDI routing; applies the next time a device connects
+              </p>
+            </div>
+            <Input
+              size="sm"
+              className="w-48"
+              value={settings.midiClientName}
+              onValueChange={(value) => updateSettings({ midiClientName: value })}
+              placeholder="opengrader-midi-input"
+            />
+          </div>
+
+          {/* Auto-connect */}
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">Auto-connect Last Device</h4>
+              <p className="text-xs text-default-500">
+                Reconnect the last MIDI input that was connected when openGRADER starts
+              </p>
+            </div>
+            <Switch
+              isSelected={settings.autoConnectLastPort}
+              onValueChange={(value) => updateSettings({ autoConnectLastPort: value })}
+            />
+          </div>
+
+          {/* MIDI Monitor Filter */}
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">Hide Clock Messages</h4>
+              <p className="text-xs text-default-500">
+                Keep MIDI clock and active sensing out of the MIDI monitor
+              </p>
+            </div>
+            <Switch
+              isSelected={settings.suppressRealtimeEvents}
+              onValueChange={(value) => updateSettings({ suppressRealtimeEvents: value })}
+            />
+          </div>
+
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">Monitored Message Types</h4>
+              <p className="text-xs text-default-500">
+                Only these reach the MIDI monitor; leave empty to show all. Macros still see every message
+              </p>
+            </div>
+            <Select
+              aria-label="Monitored message types"
+              size="sm"
+              className="w-48"
+              selectionMode="multiple"
+              placeholder="All"
+              selectedKeys={settings.emitMessageTypes}
+              onSelectionChange={(keys) => updateSettings({ emitMessageTypes: Array.from(keys as Set<string>) })}
+            >
+              {MIDI_MESSAGE_TYPES.map((type) => (
+                <SelectItem key={type}>{type}</SelectItem>
+              ))}
+            </Select>
+          </div>
+
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">MIDI Monitor Rate Limit</h4>
+                <p className="text-xs text-default-500">
+                  Events per message type per 100ms sent to the monitor; keeps busy streams from slowing the UI
+                </p>
+              </div>
+              <div className="text-right">
+                <span className="text-sm font-mono">
+                  {settings.maxMidiEventsPer100ms === 0 ? "Unlimited" : settings.maxMidiEventsPer100ms}
+                </span>
+              </div>
+            </div>
+            <Slider
+              size="sm"
+              step={5}
+              minValue={0}
+              maxValue={200}
+              value={settings.maxMidiEventsPer100ms}
+              onChange={(value) => updateSettings({ maxMidiEventsPer100ms: Array.isArray(value) ? value[0] : value })}
+              className="max-w-md"
+            />
+          </div>
+
+          {/* Dry Run */}
+          <div className="flex justify-between items-center">
+            <div>
+              <h4 className="text-sm font-medium">Dry Run</h4>
+              <p className="text-xs text-default-500">
+                Log macro actions without moving the mouse or pressing keys (delays are still honored)
+              </p>
+            </div>
+            <Switch
+              isSelected={settings.dryRun}
+              onValueChange={(value) => updateSettings({ dryRun: value })}
+              color="warning"
+            />
+          </div>
+
           {/* Default Timeout */}
           <div className="space-y-3">
             <div className="flex justify-between items-center">
@@ -138,6 +500,80 @@ export const SettingsPage: React.FC = () => {
               Range: 100ms - 30,000ms (30 seconds). Values below 100ms will be set to 100ms.
             </p>
           </div>
+
+          {/* Forbidden Regions */}
+          <div className="space-y-3">
+            <div className="flex justify-between items-center">
+              <div>
+                <h4 className="text-sm font-medium">Forbidden Screen Regions</h4>
+                <p className="text-xs text-default-500">
+                  Mouse moves that would land, and clicks or drags that would press, inside these rectangles are refused. Leave Monitor empty for global coordinates
+                </p>
+              </div>
+              <Button
+                size="sm"
+                variant="flat"
+                startContent={<Icon icon="lucide:plus" />}
+                onPress={() => updateSettings({
+                  forbiddenRegions: [...settings.forbiddenRegions, { x: 0, y: 0, width: 100, height: 100 }]
+                })}
+              >
+                Add Region
+              </Button>
+            </div>
+            {settings.forbiddenRegions.map((region, index) => {
+              const updateRegion = (patch: Partial<ForbiddenRegion>) =>
+                updateSettings({
+                  forbiddenRegions: settings.forbiddenRegions.map((r, i) => (i === index ? { ...r, ...patch } : r))
+                });
+              const numberField = (field: "x" | "y" | "width" | "height") => (
+                <Input
+                  key={field}
+                  type="number"
+                  size="sm"
+                  label={field}
+                  className="w-24"
+                  value={region[field].toString()}
+                  onValueChange={(value) => updateRegion({ [field]: parseInt(value) || 0 })}
+                />
+              );
+              return (
+                <div key={index} className="flex items-center gap-2">
+                  <Input
+                    size="sm"
+                    label="Label"
+                    className="w-36"
+                    value={region.label ?? ""}
+                    onValueChange={(value) => updateRegion({ label: value || undefined })}
+                  />
+                  {numberField("x")}
+                  {numberField("y")}
+                  {numberField("width")}
+                  {numberField("height")}
+                  <Input
+                    type="number"
+                    size="sm"
+                    label="Monitor"
+                    className="w-24"
+                    value={region.monitor?.toString() ?? ""}
+                    onValueChange={(value) => updateRegion({ monitor: value === "" ? undefined : parseInt(value) })}
+                  />
+                  <Button
+                    isIconOnly
+                    size="sm"
+                    variant="light"
+                    color="danger"
+                    aria-label="Remove region"
+                    onPress={() => updateSettings({
+                      forbiddenRegions: settings.forbiddenRegions.filter((_, i) => i !== index)
+                    })}
+                  >
+                    <Icon icon="lucide:trash-2" />
+                  </Button>
+                </div>
+              );
+            })}
+          </div>
         </CardBody>
       </Card>
 
@@ -1,7 +1,7 @@
 import React, { createContext, useContext, useState, useEffect, useCallback, useRef } from "react";
 import { invoke } from '@tauri-apps/api/core';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
-import { listenToMidiStatus, registerMacro, ActionType, ActionParams, MacroAction, MacroConfig } from "../lib/tauri";
+import { listenToMidiStatus, listenToAutoConnect, getAutoConnectStatus, registerMacro, ActionType, ActionParams, MacroAction, MacroConfig } from "../lib/tauri";
 import { addToast } from "@heroui/react";
 import { MacroDefinition, Action } from "../types/macro";
 
@@ -17,6 +17,9 @@ interface MidiContextType {
     note?: number;
     controller?: number;
     value?: number;
+    sysex?: string; // Hex payload for sysex messages
+    nrpn?: { param: number; value: number }; // Set on the CC that completes an NRPN
+    rawVelocity?: number; // NoteOn velocity as received, when the velocity curve changed it
     timestamp: number;
   // Optional macro association for quick navigation from the monitor
   macroId?: string;
@@ -28,6 +31,9 @@ interface MidiContextType {
     note?: number;
     controller?: number;
     value?: number;
+    sysex?: string; // Hex payload for sysex messages
+    nrpn?: { param: number; value: number }; // Set on the CC that completes an NRPN
+    rawVelocity?: number; // NoteOn velocity as received, when the velocity curve changed it
     timestamp: number;
   macroId?: string;
   macroName?: string;
@@ -41,13 +47,17 @@ interface RustMidiEvent {
   status: number;
   data1: number;
   data2: number;
-  timestamp: number;
+  timestamp: number; // Microseconds since the backend's MIDI clock started
   type_name: string;
-  channel: number;
+  channel: number; // 1-16, or 0 for system messages
   note?: number;
   velocity?: number;
   controller?: number;
   value?: number;
+  sysex_hex?: string; // For sysex, e.g. "F0 7E 7F 06 01 F7"
+  nrpn_param?: number | null; // For the CC that completes an NRPN, 0-16383
+  nrpn_value?: number | null;
+  raw_velocity?: number | null; // For noteon, the velocity before the velocity curve when it differs
 }
 
 const MidiContext = createContext<MidiContextType | null>(null);
@@ -194,6 +204,7 @@ const createMacroConfig = (macro: MacroDefinition): MacroConfig => {
     midi_note: macro.trigger.controller || macro.trigger.note || 0,
     midi_channel: macro.trigger.channel || 0,
     midi_value: macro.trigger.type === "controlchange" ? macro.trigger.value : undefined,
+    trigger_on: macro.trigger.type === "noteon" ? "NoteOn" : macro.trigger.type === "noteoff" ? "NoteOff" : undefined,
     actions: macro.actions.map(convertAction),
     // Include before actions if they exist
     before_actions: macro.beforeActions && macro.beforeActions.length > 0 
@@ -219,6 +230,9 @@ export const MidiProvider: React.FC<{ children: React.ReactNode }> = ({ children
     note?: number;
     controller?: number;
     value?: number;
+    sysex?: string; // Hex payload for sysex messages
+    nrpn?: { param: number; value: number }; // Set on the CC that completes an NRPN
+    rawVelocity?: number; // NoteOn velocity as received, when the velocity curve changed it
     timestamp: number;
   macroId?: string;
   macroName?: string;
@@ -229,6 +243,9 @@ export const MidiProvider: React.FC<{ children: React.ReactNode }> = ({ children
     note?: number;
     controller?: number;
     value?: number;
+    sysex?: string; // Hex payload for sysex messages
+    nrpn?: { param: number; value: number }; // Set on the CC that completes an NRPN
+    rawVelocity?: number; // NoteOn velocity as received, when the velocity curve changed it
     timestamp: number;
   macroId?: string;
   macroName?: string;
@@ -314,6 +331,11 @@ export const MidiProvider: React.FC<{ children: React.ReactNode }> = ({ children
             note: payload.note,
             controller: payload.controller,
             value: payload.value || payload.velocity, // Use velocity if value is not present (e.g. for noteon)
+            sysex: payload.sysex_hex,
+            nrpn: payload.nrpn_param != null && payload.nrpn_value != null
+              ? { param: payload.nrpn_param, value: payload.nrpn_value }
+              : undefined,
+            rawVelocity: payload.raw_velocity ?? undefined,
             timestamp: Date.now()
           };
           // Try to associate this event with a macro, preferring active ones
@@ -550,6 +572,25 @@ export const MidiProvider: React.FC<{ children: React.ReactNode }> = ({ children
       }
     };
   }, [addToast]);
+
+  // Show how the startup auto-connect went, whether it finished before or after we started listening
+  useEffect(() => {
+    let shown = false;
+    const showStatus = (status: string | null) => {
+      if (!status || shown) return;
+      shown = true;
+      addToast({
+        title: "MIDI Auto-connect",
+        description: status,
+        color: status.startsWith("Auto-connected") ? "success" : "warning"
+      });
+    };
+    const unlisten = listenToAutoConnect(showStatus);
+    getAutoConnectStatus()
+      .then(showStatus)
+      .catch(err => console.error("[MidiProvider] Failed to read auto-connect status:", err));
+    return unlisten;
+  }, [addToast]);
     
   // Periodically refresh input list to detect new devices and disconnections
   useEffect(() => {
@@ -1,6 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import { AppSettings } from "../types/settings";
+import { AppSettings, ForbiddenRegion, LogLevel } from "../types/settings";
 
 // Types that match our Rust structs
 export enum ActionType {
@@ -12,6 +12,22 @@ export enum ActionType {
   MouseRelease = "MouseRelease",
   MouseDrag = "MouseDrag",
   Delay = "Delay",
+  TypeText = "TypeText",
+  MouseMoveSmooth = "MouseMoveSmooth",
+  KeyTap = "KeyTap", // Tap key `amount` times, `duration` ms apart
+  SendMidi = "SendMidi", // Send midi_bytes to the connected MIDI output
+  WithModifiers = "WithModifiers", // Hold modifiers while running the nested actions
+  RawKey = "RawKey", // Click platform-specific keycode in `amount` (not portable across OSes)
+  ScrollSmooth = "ScrollSmooth", // Scroll `amount` clicks towards `direction`, spread over `duration` ms
+  SetLayer = "SetLayer", // Make `text` the active macro layer
+  WaitForPixel = "WaitForPixel", // Wait until pixel x/y is color `text` ("#RRGGBB", ± `amount`); stops the macro after `duration` ms (default 5000)
+  RunMacro = "RunMacro", // Run the actions of the registered macro whose id is `text` (nesting limited to 8 levels)
+  KeepAwake = "KeepAwake", // Nudge the mouse 1px and back every `amount` ms (default 60000) for `duration` ms
+  MoveAndClick = "MoveAndClick", // Move to x/y (by x/y when `relative`), then click `button` `amount` times
+  KeyHoldFor = "KeyHoldFor", // Hold `key` for `duration` ms, then release it (released early if the macro is retriggered)
+  FocusWindow = "FocusWindow", // Bring the first window whose title contains `text` to the front
+  LatchModifier = "LatchModifier", // Hold `key` around the next key-producing action of any macro; latching it again unlatches
+  DragTo = "DragTo", // Press `button` at x/y (default: cursor position), move to end_x/end_y over `duration` ms, release
 }
 
 export interface ActionParams {
@@ -24,29 +40,93 @@ export interface ActionParams {
   relative?: boolean;
   hold?: boolean;
   duration?: number;
-  amount?: number;
+  amount?: number; // MouseClick: click count (2 = double click); scroll buttons: scroll amount
+  text?: string;
+  easing?: "linear" | "ease-in-out";
+  jitter_ms?: number; // Delay: randomize duration by up to ± this many ms
+  monitor?: number; // Absolute mouse moves: x/y relative to this monitor (index from listMonitors)
+  midi_bytes?: number[]; // SendMidi: raw message, e.g. [0x90, 36, 127]
+  actions?: MacroAction[]; // WithModifiers: actions run while the modifiers are held
+  direction?: "up" | "down" | "left" | "right"; // ScrollSmooth, defaults to "down"
+  hold_duration_ms?: number; // KeyCombination: keep all keys down this long before releasing
+  probability?: number; // Chance (0-1) that the action runs each time; absent = always
+  end_x?: number; // DragTo: where the drag ends
+  end_y?: number;
+  note_value?: string; // Delay: musical length at the MIDI clock tempo ("1/4", "1/8d", "1/8t"); falls back to duration without a clock
+  window_title?: string; // MouseMove/MoveAndClick/DragTo: x/y (and end_x/end_y) relative to the first window whose title contains this
+}
+
+export interface VelocityScaling {
+  target: "Amount" | "Duration";
+  min: number; // Value at velocity 0
+  max: number; // Value at velocity 127
+  curve?: "Linear" | "Exponential";
 }
 
 // New interface to represent an action within before/after actions arrays
 export interface MacroAction {
   action_type: ActionType;
   action_params: ActionParams;
+  velocity_scaling?: VelocityScaling; // Scale a param by the triggering NoteOn velocity
+  condition?: ActionCondition; // Skip the action unless this holds when it runs
+}
+
+export interface ActionCondition {
+  active_app?: string; // Case-insensitive substring of the frontmost app's name
 }
 
 export interface MacroConfig {
   id: string;
+  config_version?: number; // Schema version; set by the backend when the macro is stored
   name: string;
   groupId?: string; // For encoder groups to share state
-  midi_note: number; // For CC, this is the CC number. For Notes, the note number.
-  midi_channel: number;
+  midi_note: number; // For CC, this is the CC number. For Notes, the note number. For Program Change, the program number.
+  midi_channel: number; // 1-16, or 0 to match any channel
+  midi_note_min?: number; // Inclusive note range, used instead of midi_note when both are set
+  midi_note_max?: number;
   midi_value?: number; // For CC, this is the CC value. For Notes, could be velocity.
+  midi_value_min?: number; // CC: inclusive value zone, used instead of midi_value; a missing bound is open
+  midi_value_max?: number;
+  midi_value_tolerance?: number; // CC: also match values within ± this of midi_value
+  nrpn_param?: number; // 14-bit NRPN parameter (CC 99/98); fires on each value sent for it
+  midi_value_14bit?: number; // 14-bit CC value (0-16383), MSB on midi_note and LSB on midi_note + 32
+  pitch_threshold?: number; // Signed pitch-bend threshold (-8192..8191)
+  pitch_direction?: "Up" | "Down"; // Crossing direction for pitch_threshold
+  pressure_threshold?: number; // Channel pressure / poly aftertouch (on midi_note) rising to this fires; re-arms below it
+  sysex_pattern?: number[]; // Fires on SysEx messages starting with these bytes (including 0xF0)
+  sysex_exact?: boolean; // Require the whole SysEx message to equal sysex_pattern
   actions: MacroAction[];
+  repeat?: number; // Run actions this many times per trigger; 0 = until retriggered
   // New fields for before/after actions
   before_actions?: MacroAction[];
+  before_every_n?: number; // Also re-run before_actions on every Nth trigger of the session; absent = once per session
+  max_flow_ms?: number; // Abandon the trigger's before + main actions after this long and emit macro-timeout; 0 = no limit
   after_actions?: MacroAction[];
-  timeout?: number; // in milliseconds
+  timeout?: number; // in milliseconds; defaults to the global default timeout when after_actions are set
+  debounce_ms?: number; // Ignore repeat MIDI triggers of this macro within this window
+  enabled?: boolean; // Disabled macros stay registered but never trigger (absent = enabled)
+  encoder_mode?: EncoderMode; // How CC values are read; relative modes fire on any movement
+  increment_actions?: MacroAction[]; // Relative encoders: run instead of actions when turned up
+  decrement_actions?: MacroAction[]; // Relative encoders: run instead of actions when turned down
+  toggle?: boolean; // Alternate between actions (odd presses) and alternate_actions (even presses)
+  alternate_actions?: MacroAction[];
+  trigger_on?: TriggerEdge; // Which note edge fires the macro (absent = both)
+  layer?: string; // Only trigger while this layer is active (absent = every layer)
+  momentary?: boolean; // Release the macro's held keys/buttons on the matching NoteOff
+  schedule?: ScheduleSpec; // Also trigger on a timer, independent of MIDI
 }
 
+// Interval: every `ms` (min 100). Daily: once a day at this local time.
+export type ScheduleSpec =
+  | { Interval: { ms: number } }
+  | { Daily: { hour: number; minute: number } };
+
+// Velocity-0 NoteOn messages count as NoteOff
+export type TriggerEdge = "NoteOn" | "NoteOff" | "Both";
+
+// RelativeTwosComplement: 1 = +1, 127 = -1. RelativeSignedBit: 1 = +1, 65 = -1.
+export type EncoderMode = "Absolute" | "RelativeTwosComplement" | "RelativeSignedBit";
+
 // Wrapper functions for Tauri commands
 export async function moveMouse(x: number, y: number, relative: boolean = false): Promise<void> {
   return invoke("move_mouse", { x, y, relative });
@@ -64,14 +144,209 @@ export async function pressKeyCombination(keys: string[]): Promise<void> {
   return invoke("press_key_combination", { keys });
 }
 
+// Rejects with one line per invalid key or button name in the macro's actions
 export async function registerMacro(config: MacroConfig): Promise<void> {
   return invoke("register_macro", { config });
 }
 
+export interface MacroRegistrationResult {
+  id: string;
+  error: string | null; // Validation problems; null if the macro was registered
+}
+
+// Registers all valid macros in one go (saved once); invalid ones are reported per id
+export async function registerMacros(configs: MacroConfig[]): Promise<MacroRegistrationResult[]> {
+  return invoke("register_macros", { configs });
+}
+
+// Run a registered macro's full before/main/after flow without MIDI input (preview)
+export async function triggerMacro(id: string): Promise<void> {
+  return invoke("trigger_macro", { id });
+}
+
+export async function pauseSchedules(): Promise<void> {
+  return invoke("pause_schedules");
+}
+
+export async function resumeSchedules(): Promise<void> {
+  return invoke("resume_schedules");
+}
+
+// Stop MIDI from triggering macros without disconnecting; the MIDI monitor keeps updating
+export async function pauseMacros(): Promise<void> {
+  return invoke("pause_macros");
+}
+
+export async function resumeMacros(): Promise<void> {
+  return invoke("resume_macros");
+}
+
+// Called with true on macros-paused and false on macros-resumed
+export function listenToMacrosPaused(callback: (paused: boolean) => void): () => void {
+  const unlistenPaused = listen("macros-paused", () => callback(true));
+  const unlistenResumed = listen("macros-resumed", () => callback(false));
+
+  return () => {
+    unlistenPaused.then(unlistenFn => unlistenFn());
+    unlistenResumed.then(unlistenFn => unlistenFn());
+  };
+}
+
 export async function getMacros(): Promise<MacroConfig[]> {
   return invoke("get_macros");
 }
 
+// Macros whose groupId is groupId; pass null for the ungrouped ones
+export async function getMacrosByGroup(groupId: string | null): Promise<MacroConfig[]> {
+  return invoke("get_macros_by_group", { groupId });
+}
+
+// Debug snapshot of groups with pending after_actions
+export interface ActiveMacroInfo {
+  group_key: string;
+  last_triggered_ms: number; // Milliseconds since the Unix epoch
+  has_before_state: boolean;
+  has_before_notifier: boolean;
+}
+
+export async function getActiveMacros(): Promise<ActiveMacroInfo[]> {
+  return invoke("get_active_macros");
+}
+
+export async function loadMacros(): Promise<MacroConfig[]> {
+  return invoke("load_macros");
+}
+
+export async function setMacroEnabled(id: string, enabled: boolean): Promise<void> {
+  return invoke("set_macro_enabled", { id, enabled });
+}
+
+export type ActionPhase = "before" | "main" | "increment" | "decrement" | "alternate" | "after";
+
+// Move one action within a phase (to = its index after the move); persisted server-side.
+// Resolves with the phase's actions in their new order.
+export async function reorderActions(macroId: string, phase: ActionPhase, from: number, to: number): Promise<MacroAction[]> {
+  return invoke("reorder_actions", { macroId, phase, from, to });
+}
+
+export async function renameMacro(id: string, newName: string): Promise<void> {
+  return invoke("rename_macro", { id, newName });
+}
+
+// Moves every macro of oldGroup to newGroup, closing the group's current session (pending
+// after-actions run first). Resolves with the number of macros renamed; rejects if newGroup is already taken.
+export async function renameGroup(oldGroup: string, newGroup: string): Promise<number> {
+  return invoke("rename_group", { oldGroup, newGroup });
+}
+
+// Clear a stuck group's session, pending after_actions and before-action state; emits "group-reset"
+// with the group id. Accepts a group id or an ungrouped macro's id
+export async function resetGroup(groupId: string): Promise<void> {
+  return invoke("reset_group", { groupId });
+}
+
+// Layers start at "default"; switching emits a "layer-changed" event with the new name
+export async function setActiveLayer(name: string): Promise<void> {
+  return invoke("set_active_layer", { name });
+}
+
+export async function getActiveLayer(): Promise<string> {
+  return invoke("get_active_layer");
+}
+
+export function listenToLayerChanges(callback: (layer: string) => void): () => void {
+  const unlisten = listen("layer-changed", (event) => {
+    callback(event.payload as string);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+export async function exportMacros(path: string): Promise<number> {
+  return invoke("export_macros", { path });
+}
+
+// Rejects with a list of validation problems if any imported macro is invalid
+export async function importMacros(path: string, merge: boolean): Promise<MacroConfig[]> {
+  return invoke("import_macros", { path, merge });
+}
+
+// Kill switch: stops every pending macro and releases all held keys and mouse buttons.
+// Emitting the "emergency-release" event (e.g. from a global shortcut) does the same.
+export async function emergencyRelease(): Promise<void> {
+  return invoke("emergency_release");
+}
+
+// Capture incoming MIDI (discarding the previous capture) to save and replay it later
+export async function startMidiRecording(): Promise<void> {
+  return invoke("start_midi_recording");
+}
+
+// Resolves with the number of messages captured
+export async function stopMidiRecording(): Promise<number> {
+  return invoke("stop_midi_recording");
+}
+
+export async function saveRecording(path: string): Promise<number> {
+  return invoke("save_recording", { path });
+}
+
+// Feeds a saved recording through macro matching with its original timing, like a live device.
+// Resolves with the message count once playback has started.
+export async function replayRecording(path: string): Promise<number> {
+  return invoke("replay_recording", { path });
+}
+
+// Stops a replay started by replayRecording; macros it already triggered keep running
+export async function stopReplay(): Promise<void> {
+  return invoke("stop_replay");
+}
+
+// Seed the RNG behind action probability and delay jitter for repeatable runs; null reseeds randomly
+export async function setRandomSeed(seed: number | null): Promise<void> {
+  return invoke("set_random_seed", { seed });
+}
+
+// Unregisters and stops every macro, releases held inputs and saves the empty set.
+// Resolves with how many macros were removed; a "macros-cleared" event carries the same count.
+export async function clearAllMacros(): Promise<number> {
+  return invoke("clear_all_macros");
+}
+
+export function listenToMacrosCleared(callback: (cleared: number) => void): () => void {
+  const unlisten = listen("macros-cleared", (event) => {
+    callback(event.payload as number);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+// Revert the held keys/buttons and mouse moves of a group's last main-actions run
+// (pass the macro id for ungrouped macros). Resolves with how many actions were reverted.
+export async function undoLastMacro(groupId: string): Promise<number> {
+  return invoke("undo_last_macro", { groupId });
+}
+
+// Fix stuck modifiers: releases ones the OS reports down that no macro holds (Windows/macOS)
+// and ones held by removed/disabled macros. Resolves with a description of each change.
+export async function syncModifierState(): Promise<string[]> {
+  return invoke("sync_modifier_state");
+}
+
+export interface HeldInput {
+  name: string; // Same names string_to_key / mouse buttons accept, e.g. "shift", "left"
+  macro_id: string | null; // Macro that pressed it, if any
+}
+
+// Keys and mouse buttons the app currently holds down
+export async function getInputState(): Promise<{ keys: HeldInput[]; mouse_buttons: HeldInput[] }> {
+  return invoke("get_input_state");
+}
+
 export async function executeAction(actionType: ActionType, params: ActionParams): Promise<void> {
   return invoke("execute_action", { actionType, params });
 }
@@ -82,6 +357,170 @@ export async function getCursorPosition(): Promise<[number, number]> {
   return result;
 }
 
+export interface MonitorInfo {
+  index: number;
+  name: string | null;
+  x: number; // Origin in physical pixels
+  y: number;
+  width: number;
+  height: number;
+  scale_factor: number;
+}
+
+export async function listMonitors(): Promise<MonitorInfo[]> {
+  return invoke("list_monitors");
+}
+
+export interface MidiLearnResult {
+  type_name: string;
+  channel: number;
+  data1: number;
+  data2: number;
+}
+
+export async function midiLearn(timeoutSecs?: number): Promise<MidiLearnResult> {
+  return invoke("midi_learn", { timeoutSecs });
+}
+
+// Payload of the macro-complete and after-actions-complete events
+export interface MacroCompleteEvent {
+  macro_id: string;
+  macro_name: string;
+  group_id: string | null;
+  duration_ms: number;
+  aborted: boolean; // Cut short by a newer trigger of the same group
+}
+
+// Payload of the macro-timeout event emitted when a trigger runs past its max_flow_ms
+export interface MacroTimeoutEvent {
+  macro_id: string;
+  macro_name: string;
+  group_id: string | null;
+  max_flow_ms: number;
+}
+
+export function listenToMacroTimeouts(callback: (event: MacroTimeoutEvent) => void): () => void {
+  const unlisten = listen("macro-timeout", (event) => {
+    callback(event.payload as MacroTimeoutEvent);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+// Payload of the macro-action-error event emitted when an action of a macro fails
+export interface MacroActionErrorEvent {
+  macro_id: string;
+  phase: string; // "before", "main", "after", ...
+  action_index: number; // Index within the phase's actions (or within the nested list)
+  action_type: ActionType;
+  error: string;
+}
+
+export function listenToMacroActionErrors(callback: (event: MacroActionErrorEvent) => void): () => void {
+  const unlisten = listen("macro-action-error", (event) => {
+    callback(event.payload as MacroActionErrorEvent);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+// Payload of the midi-flood-detected event, emitted at most once a second while triggers are dropped
+export interface MidiFloodEvent {
+  macro_id: string; // Latest macro whose trigger was dropped
+  in_flight: number;
+  limit: number;
+}
+
+// Payload of the blocked-move event emitted when a mouse move targets a forbidden region
+export interface BlockedMoveEvent {
+  action_type: ActionType;
+  x: number; // Global point the move would have landed on or the button pressed at
+  y: number;
+  region: ForbiddenRegion;
+}
+
+export function listenToBlockedMoves(callback: (event: BlockedMoveEvent) => void): () => void {
+  const unlisten = listen("blocked-move", (event) => {
+    callback(event.payload as BlockedMoveEvent);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+// Payload of the dry-run-action event emitted instead of executing an action
+export interface DryRunActionEvent {
+  action_type: ActionType;
+  action_params: ActionParams;
+  timestamp_ms: number;
+}
+
+// Connect an additional MIDI input; already-open ports stay connected, and starting one
+// that is already open is a no-op that reports "Already connected" on midi-status
+export async function startMidiPort(portIndex: number): Promise<void> {
+  return invoke("start_midi_listening_rust", { portIndex });
+}
+
+export interface ActionHistoryEntry {
+  timestamp_ms: number; // Milliseconds since the Unix epoch, when the action finished
+  macro_id: string;
+  phase: string; // "before", "main", "after", ...
+  action_type: ActionType;
+  error: string | null; // null if the action succeeded
+}
+
+// Most recently executed macro actions, oldest first (up to actionHistorySize)
+export async function getActionHistory(): Promise<ActionHistoryEntry[]> {
+  return invoke("get_action_history");
+}
+
+export interface MidiStats {
+  since_ms: number; // How long the counters have been running (reset when a port connects)
+  total_messages: number;
+  by_type: Record<string, number>; // Keyed like emitMessageTypes, e.g. "noteon"
+  messages_per_second: number; // Over the last second
+  macro_triggers: Record<string, number>; // By macro id
+}
+
+export async function getMidiStats(): Promise<MidiStats> {
+  return invoke("get_midi_stats");
+}
+
+// Feed raw MIDI bytes (e.g. [0x90, 36, 127]) through the same pipeline as a connected input
+// Only available in builds with the backend's midi-injection feature
+export async function injectMidiMessage(bytes: number[]): Promise<void> {
+  return invoke("inject_midi_message", { bytes });
+}
+
+export interface MidiPortInfo {
+  index: number; // What startMidiPort takes; can change as devices come and go
+  name: string;
+  id: string; // OS identifier; tells apart ports with the same name
+  occurrence: number; // 1 for the first port with this name, 2 for the second, ...
+}
+
+export async function listMidiInputsDetailed(): Promise<MidiPortInfo[]> {
+  return invoke("list_midi_inputs_detailed");
+}
+
+export async function listMidiOutputs(): Promise<string[]> {
+  return invoke("list_midi_outputs");
+}
+
+// Output port that SendMidi actions write to; replaces any previous one
+export async function connectMidiOutput(portIndex: number): Promise<void> {
+  return invoke("connect_midi_output", { portIndex });
+}
+
+export async function stopMidiPort(portIndex: number): Promise<void> {
+  return invoke("stop_midi_port", { portIndex });
+}
+
 export function listenToMidiStatus(callback: (status: string) => void): () => void {
   const unlisten = listen("midi-status", (event) => {
     callback(event.payload as string);
@@ -92,11 +531,73 @@ export function listenToMidiStatus(callback: (status: string) => void): () => vo
   };
 }
 
+// Outcome of the startup auto-connect to the last MIDI device; null until it has run
+export async function getAutoConnectStatus(): Promise<string | null> {
+  return invoke("get_auto_connect_status");
+}
+
+// Also emitted when the startup auto-connect finishes, for a frontend that is already listening
+export function listenToAutoConnect(callback: (status: string) => void): () => void {
+  const unlisten = listen("midi-auto-connect", (event) => {
+    callback(event.payload as string);
+  });
+
+  return () => {
+    unlisten.then(unlistenFn => unlistenFn());
+  };
+}
+
+export interface AppInfo {
+  version: string;
+  tauri_version: string;
+  os: string; // e.g. "windows", "macos", "linux"
+  arch: string; // e.g. "x86_64", "aarch64"
+  debug: boolean; // Debug build
+}
+
+export async function getAppInfo(): Promise<AppInfo> {
+  return invoke("get_app_info");
+}
+
+export interface SelfTestCheck {
+  name: string; // "accessibility" (macOS only), "input_init", "mouse_read", "mouse_move" or "midi_input"
+  passed: boolean;
+  detail?: string; // Why it failed, or extra context when it passed
+}
+
+export interface SelfTestReport {
+  os: string;
+  passed: boolean; // Every check passed
+  checks: SelfTestCheck[];
+}
+
+// Checks input permissions and devices; harmless to run at any time
+export async function runSelfTest(): Promise<SelfTestReport> {
+  return invoke("run_self_test");
+}
+
 // Global settings interface for Rust backend
 interface RustGlobalSettings {
   macro_trigger_delay: number;
   enable_macro_conflict_prevention: boolean;
   default_timeout: number;
+  dry_run: boolean;
+  enigo_init_delay_ms: number;
+  log_level: LogLevel;
+  action_timeout_ms: number;
+  max_concurrent_macros: number;
+  midi_client_name: string;
+  velocity_gamma: number;
+  suppress_realtime_events: boolean;
+  emit_message_types: string[];
+  max_midi_events_per_100ms: number;
+  auto_connect_last_port: boolean;
+  scroll_acceleration: number;
+  scroll_acceleration_window_ms: number;
+  scroll_acceleration_curve: number;
+  forbidden_regions: ForbiddenRegion[];
+  action_history_size: number;
+  default_move_duration_ms: number;
 }
 
 // Convert frontend settings to Rust format
@@ -105,6 +606,23 @@ function toRustSettings(settings: AppSettings): RustGlobalSettings {
     macro_trigger_delay: settings.macroTriggerDelay,
     enable_macro_conflict_prevention: settings.enableMacroConflictPrevention,
     default_timeout: settings.defaultTimeout,
+    dry_run: settings.dryRun,
+    enigo_init_delay_ms: settings.enigoInitDelayMs,
+    log_level: settings.logLevel,
+    action_timeout_ms: settings.actionTimeoutMs,
+    max_concurrent_macros: settings.maxConcurrentMacros,
+    midi_client_name: settings.midiClientName,
+    velocity_gamma: settings.velocityGamma,
+    suppress_realtime_events: settings.suppressRealtimeEvents,
+    emit_message_types: settings.emitMessageTypes,
+    max_midi_events_per_100ms: settings.maxMidiEventsPer100ms,
+    auto_connect_last_port: settings.autoConnectLastPort,
+    scroll_acceleration: settings.scrollAcceleration,
+    scroll_acceleration_window_ms: settings.scrollAccelerationWindowMs,
+    scroll_acceleration_curve: settings.scrollAccelerationCurve,
+    forbidden_regions: settings.forbiddenRegions,
+    action_history_size: settings.actionHistorySize,
+    default_move_duration_ms: settings.defaultMoveDurationMs,
   };
 }
 
@@ -114,6 +632,23 @@ function fromRustSettings(rustSettings: RustGlobalSettings): AppSettings {
     macroTriggerDelay: rustSettings.macro_trigger_delay,
     enableMacroConflictPrevention: rustSettings.enable_macro_conflict_prevention,
     defaultTimeout: rustSettings.default_timeout,
+    dryRun: rustSettings.dry_run,
+    enigoInitDelayMs: rustSettings.enigo_init_delay_ms,
+    logLevel: rustSettings.log_level,
+    actionTimeoutMs: rustSettings.action_timeout_ms,
+    maxConcurrentMacros: rustSettings.max_concurrent_macros,
+    midiClientName: rustSettings.midi_client_name,
+    velocityGamma: rustSettings.velocity_gamma,
+    suppressRealtimeEvents: rustSettings.suppress_realtime_events,
+    emitMessageTypes: rustSettings.emit_message_types,
+    maxMidiEventsPer100ms: rustSettings.max_midi_events_per_100ms,
+    autoConnectLastPort: rustSettings.auto_connect_last_port,
+    scrollAcceleration: rustSettings.scroll_acceleration,
+    scrollAccelerationWindowMs: rustSettings.scroll_acceleration_window_ms,
+    scrollAccelerationCurve: rustSettings.scroll_acceleration_curve,
+    forbiddenRegions: rustSettings.forbidden_regions,
+    actionHistorySize: rustSettings.action_history_size,
+    defaultMoveDurationMs: rustSettings.default_move_duration_ms,
   };
 }
 
@@ -125,4 +660,20 @@ export async function getGlobalSettings(): Promise<AppSettings> {
 export async function updateGlobalSettings(settings: AppSettings): Promise<void> {
   const rustSettings = toRustSettings(settings);
   await invoke("update_global_settings", { newSettings: rustSettings });
-} 
\ No newline at end of file
+} 
+
+// AppSettings keys are the camelCase forms of the Rust field names (maxMidiEventsPer100ms -> max_midi_events_per_100ms)
+function toRustSettingKey(key: keyof AppSettings): string {
+  return key.replace(/[A-Z]|\d+/g, (part) => "_" + part.toLowerCase());
+}
+
+export async function getSetting<K extends keyof AppSettings>(key: K): Promise<AppSettings[K]> {
+  return invoke("get_setting", { key: toRustSettingKey(key) });
+}
+
+// Change one setting without sending the rest back, so concurrent edits elsewhere aren't lost.
+// Resolves with all settings after the change; rejects on a value of the wrong type.
+export async function setSetting<K extends keyof AppSettings>(key: K, value: AppSettings[K]): Promise<AppSettings> {
+  const rustSettings: RustGlobalSettings = await invoke("set_setting", { key: toRustSettingKey(key), value });
+  return fromRustSettings(rustSettings);
+}
\ No newline at end of file
@@ -2,10 +2,56 @@ export interface AppSettings {
   macroTriggerDelay: number; // Delay in milliseconds before triggering a new macro
   enableMacroConflictPrevention: boolean; // Whether to prevent conflicting macro triggers
   defaultTimeout: number; // Default timeout for new macros in milliseconds
+  dryRun: boolean; // Log actions as dry-run-action events instead of executing them
+  enigoInitDelayMs: number; // Settle time before each input-simulation call; 0 = none
+  logLevel: LogLevel; // Minimum level written to the console and the log file
+  actionTimeoutMs: number; // Skip a single macro action that runs longer than this; 0 = no limit
+  maxConcurrentMacros: number; // Drop triggers while this many macros are running; 0 = no limit
+  midiClientName: string; // How the app's MIDI inputs are named in the OS MIDI graph; used for new connections
+  velocityGamma: number; // NoteOn velocity curve: < 1 boosts soft hits, > 1 softens them, 1 = unchanged
+  suppressRealtimeEvents: boolean; // Hide clock/active-sensing messages from the MIDI monitor
+  emitMessageTypes: string[]; // Message types shown in the MIDI monitor (e.g. "noteon"); empty = all
+  maxMidiEventsPer100ms: number; // Per message type; macros still see every message; 0 = no limit
+  autoConnectLastPort: boolean; // Reconnect the last connected MIDI input on startup
+  scrollAcceleration: number; // Max scroll amount multiplier for fast encoder spins; 1 = off
+  scrollAccelerationWindowMs: number; // Triggers closer together than this speed scrolling up
+  scrollAccelerationCurve: number; // 1 = linear; higher only accelerates the fastest spins
+  forbiddenRegions: ForbiddenRegion[]; // Mouse moves landing and clicks or drags pressing inside these are refused
+  actionHistorySize: number; // Executed actions kept for the activity feed; 0 = don't record
+  defaultMoveDurationMs: number; // Animate mouse moves without their own duration over this long; 0 = jump
 }
+
+// Screen rectangle mouse moves must not land in; global coordinates, or relative to `monitor`
+export interface ForbiddenRegion {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  monitor?: number;
+  label?: string;
+}
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
  
 export const DEFAULT_SETTINGS: AppSettings = {
   macroTriggerDelay: 0, // 0ms default delay (no delay)
   enableMacroConflictPrevention: true,
-  defaultTimeout: 500
+  defaultTimeout: 500,
+  dryRun: false,
+  enigoInitDelayMs: 0,
+  logLevel: "info",
+  actionTimeoutMs: 0,
+  maxConcurrentMacros: 16,
+  midiClientName: "opengrader-midi-input",
+  velocityGamma: 1,
+  suppressRealtimeEvents: true,
+  emitMessageTypes: [],
+  maxMidiEventsPer100ms: 50,
+  autoConnectLastPort: true,
+  scrollAcceleration: 1,
+  scrollAccelerationWindowMs: 150,
+  scrollAccelerationCurve: 2,
+  forbiddenRegions: [],
+  actionHistorySize: 200,
+  defaultMoveDurationMs: 0
 }; 
\ No newline at end of file